@@ -1,5 +1,6 @@
 use crate::context::Context;
 use mockall::automock;
+use std::path::PathBuf;
 use thiserror::Error;
 
 /// An action to run in the background and restart a subprocess.
@@ -21,6 +22,19 @@ pub enum ActionError {
     /// Running action failed. It is usually a runtime issue.
     #[error("{0}")]
     FailedAction(String),
+    /// The action exited with a non-zero exit code.
+    #[error("{0}")]
+    NonZeroExitCode(String, i32),
+}
+
+impl ActionError {
+    /// The exit code of the action, if it exited with one.
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            ActionError::NonZeroExitCode(_, code) => Some(*code),
+            _ => None,
+        }
+    }
 }
 
 /// An action is a process that runs if any changes occured.
@@ -32,4 +46,49 @@ pub enum ActionError {
 pub trait Action {
     /// Initiate the action
     fn run(&mut self, context: &Context) -> Result<(), ActionError>;
+    /// The name of the action to show in reports (usually the command), by default the type name.
+    fn name(&self) -> String {
+        let type_name = std::any::type_name::<Self>();
+        type_name
+            .rsplit("::")
+            .next()
+            .unwrap_or(type_name)
+            .to_string()
+    }
+    /// Check if the action should run for the changes in the context, by default it always runs.
+    fn is_relevant(&self, _context: &Context) -> bool {
+        true
+    }
+    /// With release directories, run the action only after switching to the new release
+    /// (e.g. restarting a background process from it).
+    fn runs_after_activation(&self) -> bool {
+        false
+    }
+    /// The directory that the action keeps using after it ran (e.g. where the process is running).
+    fn directory_in_use(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct CustomAction;
+
+    impl Action for CustomAction {
+        fn run(&mut self, _context: &Context) -> Result<(), ActionError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_should_name_and_run_custom_actions_by_default() {
+        let action = CustomAction;
+        assert_eq!("CustomAction", action.name());
+        assert!(action.is_relevant(&HashMap::new()));
+        assert!(!action.runs_after_activation());
+        assert_eq!(None, action.directory_in_use());
+    }
 }
@@ -1,9 +1,11 @@
 use super::{utils::command::create_command, Action, ActionError};
-use crate::context::Context;
+use crate::{context::Context, paths::PathFilter};
 use duct::{Expression, ReaderHandle};
 use log::{debug, error, info, trace, warn};
 use std::{
+    fs,
     io::{BufRead, BufReader},
+    path::PathBuf,
     sync::{Arc, RwLock},
     thread::{self, sleep},
     time::Duration,
@@ -64,6 +66,7 @@ pub struct ProcessParams {
     #[cfg(unix)]
     stop_timeout: Duration,
     runs_in_shell: bool,
+    paths: PathFilter,
 }
 
 impl ProcessParams {
@@ -85,6 +88,7 @@ impl ProcessParams {
             #[cfg(unix)]
             stop_timeout: Duration::from_secs(10),
             runs_in_shell,
+            paths: PathFilter::default(),
         })
     }
 
@@ -92,6 +96,11 @@ impl ProcessParams {
         self.retries = retries;
     }
 
+    /// Only restart the process if a changed file matches the filter.
+    pub fn set_paths(&mut self, paths: PathFilter) {
+        self.paths = paths;
+    }
+
     #[cfg_attr(not(unix), allow(unused_variables))]
     pub fn set_stop_signal(&mut self, stop_signal: String) -> Result<(), ProcessError> {
         #[cfg(unix)]
@@ -117,6 +126,7 @@ impl ProcessParams {
 #[cfg_attr(unix, allow(dead_code))]
 pub struct Process {
     child: Arc<RwLock<Option<ReaderHandle>>>,
+    directory: Arc<RwLock<Option<PathBuf>>>,
     #[cfg(unix)]
     stop_signal: Signal,
     #[cfg(unix)]
@@ -124,7 +134,10 @@ pub struct Process {
 }
 
 impl Process {
-    fn start_child(params: &ProcessParams) -> Result<ReaderHandle, ProcessError> {
+    fn start_child(
+        params: &ProcessParams,
+        directory: &RwLock<Option<PathBuf>>,
+    ) -> Result<ReaderHandle, ProcessError> {
         info!(
             "Starting process {:?} {}in {}.",
             params.command,
@@ -152,16 +165,23 @@ impl Process {
             trace!("Started process with pid {pid}.",);
         }
 
+        // Save where the process runs, the directory might be a symlink to a release
+        if let Ok(mut directory) = directory.write() {
+            *directory = fs::canonicalize(&params.directory).ok();
+        }
+
         Ok(child)
     }
 
     fn start(params: &ProcessParams) -> Result<Process, ProcessError> {
-        let child = Arc::new(RwLock::new(Some(Process::start_child(params)?)));
+        let directory = Arc::new(RwLock::new(None));
+        let child = Arc::new(RwLock::new(Some(Process::start_child(params, &directory)?)));
 
         let command_id = params.command.clone();
         let max_retries = params.retries;
         let thread_params = params.clone();
         let thread_child = child.clone();
+        let thread_directory = directory.clone();
         thread::spawn(move || {
             let mut tries = max_retries + 1;
 
@@ -198,7 +218,7 @@ impl Process {
                 );
 
                 sleep(Duration::from_millis(100));
-                match Process::start_child(&thread_params) {
+                match Process::start_child(&thread_params, &thread_directory) {
                     Ok(new_child) => {
                         trace!("Locking the subprocess to replace the child with the new process.");
                         if let Ok(mut unlocked_child) = thread_child.write() {
@@ -235,6 +255,7 @@ impl Process {
 
         Ok(Process {
             child,
+            directory,
             #[cfg(unix)]
             stop_signal: params.stop_signal,
             #[cfg(unix)]
@@ -242,6 +263,27 @@ impl Process {
         })
     }
 
+    /// Create a process that is not running yet (e.g. there is no release to run it from).
+    fn stopped(params: &ProcessParams) -> Process {
+        Process {
+            child: Arc::new(RwLock::new(None)),
+            directory: Arc::new(RwLock::new(None)),
+            #[cfg(unix)]
+            stop_signal: params.stop_signal,
+            #[cfg(unix)]
+            stop_timeout: params.stop_timeout,
+        }
+    }
+
+    /// The directory where the process is running, if it is running.
+    fn running_directory(&self) -> Option<PathBuf> {
+        let is_running = self.child.read().is_ok_and(|child| child.is_some());
+        if !is_running {
+            return None;
+        }
+        self.directory.read().ok()?.clone()
+    }
+
     #[cfg(unix)]
     fn stop(&mut self) -> Result<(), ProcessError> {
         use duration_string::DurationString;
@@ -336,6 +378,13 @@ impl ProcessAction {
         Ok(ProcessAction { params, process })
     }
 
+    /// Creates the action without starting the process, it is started at the first run.
+    pub fn new_stopped(params: ProcessParams) -> ProcessAction {
+        let process = Process::stopped(&params);
+
+        ProcessAction { params, process }
+    }
+
     fn run_inner(&mut self) -> Result<(), ProcessError> {
         self.process
             .stop()
@@ -351,6 +400,23 @@ impl Action for ProcessAction {
     fn run(&mut self, _context: &Context) -> Result<(), ActionError> {
         Ok(self.run_inner()?)
     }
+
+    fn name(&self) -> String {
+        self.params.command.clone()
+    }
+
+    fn is_relevant(&self, context: &Context) -> bool {
+        self.params.paths.matches_changes(context)
+    }
+
+    /// The process keeps running from the release, so it is restarted after switching to it.
+    fn runs_after_activation(&self) -> bool {
+        true
+    }
+
+    fn directory_in_use(&self) -> Option<PathBuf> {
+        self.process.running_directory()
+    }
 }
 
 #[cfg(test)]
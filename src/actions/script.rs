@@ -1,5 +1,8 @@
 use super::{utils::command::create_command, Action, ActionError};
-use crate::context::Context;
+use crate::{
+    context::{to_variables, Context, TempDirectory},
+    paths::PathFilter,
+};
 use duct::Expression;
 use log::{debug, info};
 use std::io::{BufRead, BufReader};
@@ -19,6 +22,7 @@ pub struct ScriptAction {
     command: String,
     script: Expression,
     runs_in_shell: bool,
+    paths: PathFilter,
 }
 
 /// Custom error describing the error cases for the ScriptAction.
@@ -42,9 +46,11 @@ pub enum ScriptError {
 impl From<ScriptError> for ActionError {
     fn from(value: ScriptError) -> Self {
         match value {
+            ScriptError::NonZeroExitcode(code) => {
+                ActionError::NonZeroExitCode(value.to_string(), code)
+            }
             ScriptError::CommandParseFailure(_)
             | ScriptError::ScriptFailure(_)
-            | ScriptError::NonZeroExitcode(_)
             | ScriptError::OutputFailure => ActionError::FailedAction(value.to_string()),
         }
     }
@@ -63,10 +69,8 @@ impl ScriptAction {
         let script = script
             .env("CI", "true")
             .env("GW_ACTION_NAME", ACTION_NAME)
-            .env("GW_DIRECTORY", &directory)
             .stderr_to_stdout()
             .stdout_capture()
-            .dir(&directory)
             .unchecked();
 
         Ok(ScriptAction {
@@ -74,18 +78,30 @@ impl ScriptAction {
             command,
             script,
             runs_in_shell,
+            paths: PathFilter::default(),
         })
     }
 
+    /// Only run the script if a changed file matches the filter.
+    pub fn set_paths(&mut self, paths: PathFilter) {
+        self.paths = paths;
+    }
+
     fn run_inner(&self, context: &Context) -> Result<(), ScriptError> {
         // We can run `sh_dangerous`, because it is on the user's computer.
         let mut script = self.script.clone();
 
-        // Set the environment variables
-        for (key, value) in context {
-            script = script.env(format!("GW_{key}"), value);
+        // Set the environment variables, the long values are passed in files,
+        // that are removed when the directory is dropped after the script finished
+        let mut variables_directory = TempDirectory::new();
+        for (key, value) in to_variables(context, &mut variables_directory)? {
+            script = script.env(key, value);
         }
 
+        // Run in the new release, if the update is checked out separately
+        let directory = context.get("RELEASE_DIRECTORY").unwrap_or(&self.directory);
+        script = script.env("GW_DIRECTORY", directory).dir(directory);
+
         // Start the shell script
         info!(
             "Running script {:?} {}in {}.",
@@ -95,7 +111,7 @@ impl ScriptAction {
             } else {
                 ""
             },
-            self.directory,
+            directory,
         );
         let child = script.reader()?;
 
@@ -130,6 +146,14 @@ impl Action for ScriptAction {
     fn run(&mut self, context: &Context) -> Result<(), ActionError> {
         Ok(self.run_inner(context)?)
     }
+
+    fn name(&self) -> String {
+        self.command.clone()
+    }
+
+    fn is_relevant(&self, context: &Context) -> bool {
+        self.paths.matches_changes(context)
+    }
 }
 
 #[cfg(test)]
@@ -1,12 +1,18 @@
 use duration_string::DurationString;
-use gumdrop::Options;
-use gw_bin::checks::git::GitTriggerArgument;
-use std::{env, str::FromStr};
+use gumdrop::{Options, ParsingStyle};
+use gw_bin::{
+    checks::git::{
+        DeployWindow, DirtyPolicy, GitTriggerArgument, SkipScope, SkipTrailer, VersionRange,
+    },
+    start::OverlapPolicy,
+};
+use std::{env, process, str::FromStr};
 
 #[derive(Clone, Debug)]
 pub enum TriggerArgument {
     Push,
     Tag(String),
+    Semver(VersionRange),
 }
 
 impl FromStr for TriggerArgument {
@@ -19,7 +25,12 @@ impl FromStr for TriggerArgument {
             s if s.starts_with("tag:") => Ok(TriggerArgument::Tag(
                 s.trim_start_matches("tag:").to_string(),
             )),
-            s => Err(format!("cannot parse {s}, valid values: push, tag, tag:prefix")),
+            s if s.starts_with("semver:") => Ok(TriggerArgument::Semver(
+                s.trim_start_matches("semver:").parse()?,
+            )),
+            s => Err(format!(
+                "cannot parse {s}, valid values: push, tag, tag:prefix, semver:range"
+            )),
         }
     }
 }
@@ -29,6 +40,25 @@ impl From<TriggerArgument> for GitTriggerArgument {
         match value {
             TriggerArgument::Push => GitTriggerArgument::Push,
             TriggerArgument::Tag(t) => GitTriggerArgument::Tag(t),
+            TriggerArgument::Semver(range) => GitTriggerArgument::Semver(range),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum CheckArgument {
+    Git,
+    Watch,
+}
+
+impl FromStr for CheckArgument {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "git" => Ok(CheckArgument::Git),
+            "watch" => Ok(CheckArgument::Watch),
+            s => Err(format!("cannot parse {s}, valid values: git, watch")),
         }
     }
 }
@@ -58,25 +88,170 @@ pub struct Args {
     #[options(short = "P", no_long, meta = "PROCESS")]
     pub process_with_shell: Option<String>,
 
+    /// Only run the previous script or process if a changed file matches the glob (e.g. "frontend/").
+    ///
+    /// You can define it multiple times for an action, prefix the glob with `!` to exclude paths.
+    #[options(no_short, meta = "GLOB")]
+    pub when_changed: Vec<String>,
+
     /// Try to pull only once. Useful for cronjobs.
     #[options(long = "once", no_short)]
     pub once: bool,
 
-    /// The trigger on which to run (can be `push`, `tag` or `tag:pattern`).
+    /// The check to run (can be `git` or `watch`).
+    ///
+    /// The options are:
+    /// - `git`: fetch and pull the git repository,
+    /// - `watch`: look for changed files in the directory (e.g. synced with rsync).
+    #[options(no_short, long = "check", default = "git")]
+    pub check: CheckArgument,
+
+    /// Don't watch the files matching the glob (e.g. "*.log"), you can define multiple times.
+    #[options(no_short, meta = "GLOB")]
+    pub watch_ignore: Vec<String>,
+
+    /// The trigger on which to run (can be `push`, `tag`, `tag:pattern` or `semver:range`).
     ///
     /// The options are:
     /// - `push`: update on every commit,
     /// - `tag`: update on every tag on this branch,
-    /// - `tag:pattern`: update on tags matching the glob.
+    /// - `tag:pattern`: update on tags matching the glob,
+    /// - `semver:range`: update to the highest version tag in the range (e.g. "semver:^2.1").
     #[options(no_short, long = "on", default = "push")]
     pub trigger: TriggerArgument,
 
+    /// Also update to pre-release versions (e.g. "v2.2.0-rc.1") with `--on semver:range`.
+    #[options(no_short, long = "prerelease")]
+    pub prerelease: bool,
+
+    /// Only report updates changing paths matching the glob (e.g. "services/api/"), you can define multiple times.
+    ///
+    /// The repository is pulled regardless, but the actions only run if a changed file matches.
+    #[options(no_short, meta = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Ignore changes in paths matching the glob (e.g. "*.md"), you can define multiple times.
+    #[options(no_short, meta = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Pull the commits with this trailer (e.g. "Deploy: no") without deploying, you can define multiple times.
+    ///
+    /// The commits containing `[skip deploy]` or `[gw skip]` are always skipped. By default "Deploy: no".
+    #[options(no_short, meta = "TRAILER")]
+    pub skip_trailer: Vec<SkipTrailer>,
+
+    /// Which pulled commits have to be marked to skip the deploy (can be `latest`, `all` or `none`).
+    ///
+    /// The options are:
+    /// - `latest`: the newest pulled commit,
+    /// - `all`: every pulled commit,
+    /// - `none`: never skip the deploy.
+    #[options(no_short, meta = "SCOPE", default = "latest")]
+    pub skip_scope: SkipScope,
+
     /// Refreshes the repo with this interval.
     ///
     /// Can be a number postfixed with s(econd), m(inutes), h(ours), d(ays)
     #[options(long = "every", default = "1m")]
     pub delay: DurationString,
 
+    /// Refreshes the repo when the cron expression matches (e.g. "0 3 * * *"), instead of the interval.
+    #[options(no_short, meta = "EXPR")]
+    pub cron: Option<String>,
+
+    /// Only update in this window (e.g. "Mon-Thu 09:00-16:00"), you can define multiple times.
+    ///
+    /// Outside of the windows, the updates are held until a window opens.
+    #[options(no_short, meta = "WINDOW")]
+    pub deploy_window: Vec<DeployWindow>,
+
+    /// The timezone of the cron expression and the deploy windows (e.g. "Europe/Berlin"). By default the local timezone.
+    #[options(no_short, meta = "TZ")]
+    pub timezone: Option<String>,
+
+    /// What to do with triggers while the actions are running (can be `queue`, `coalesce` or `drop`).
+    ///
+    /// The options are:
+    /// - `queue`: run a check for every trigger after each other,
+    /// - `coalesce`: run one check for all of them,
+    /// - `drop`: ignore them.
+    #[options(no_short, meta = "POLICY", default = "coalesce")]
+    pub overlap: OverlapPolicy,
+
+    /// What to do with local changes before updating (can be `fail`, `ignore-untracked`, `stash` or `discard`).
+    ///
+    /// The options are:
+    /// - `fail`: don't update if there are any local changes, including untracked files,
+    /// - `ignore-untracked`: update if there are only untracked files (default),
+    /// - `stash`: stash the local changes and reapply them after updating,
+    /// - `discard`: throw away the local changes.
+    #[options(no_short, meta = "POLICY", default = "ignore-untracked")]
+    pub dirty: DirtyPolicy,
+
+    /// Ignore the local changes in paths matching the glob (e.g. "uploads/"), you can define multiple times.
+    #[options(no_short, meta = "GLOB")]
+    pub dirty_ignore: Vec<String>,
+
+    /// Reset to the remote if the branch diverged (e.g. after a force push), instead of failing.
+    ///
+    /// This throws away local commits, only use it for deploy-only checkouts.
+    #[options(no_short, long = "mirror")]
+    pub mirror: bool,
+
+    /// Roll back to the previous commit if an action fails, and run the actions again.
+    ///
+    /// The failed commit is not pulled again, until a newer commit arrives.
+    #[options(no_short, long = "rollback")]
+    pub rollback: bool,
+
+    /// Run this script in a shell after rolling back, instead of the actions. Implies --rollback.
+    #[options(no_short, meta = "SCRIPT")]
+    pub rollback_script: Option<String>,
+
+    /// Check out every update to DIR/releases/<sha> and switch the DIR/current symlink to it, after the actions succeeded.
+    ///
+    /// The actions run in the new release directory, outside of the repository.
+    #[options(no_short, meta = "DIR")]
+    pub release_dir: Option<String>,
+
+    /// The number of releases to keep, including the current one. By default 5.
+    #[options(no_short, meta = "N")]
+    pub keep_releases: Option<usize>,
+
+    /// Save every update and failed run with the results of the actions to this directory, see them with `gw history`.
+    #[options(no_short, meta = "DIR")]
+    pub state_dir: Option<String>,
+
+    /// Only pull commits signed by an SSH key in this allowed signers file (see ssh-keygen(1)).
+    #[options(no_short, meta = "FILE")]
+    pub allowed_signers: Option<String>,
+
+    /// Only pull commits signed by this GPG key fingerprint (from the keyring), you can define multiple times.
+    #[options(no_short, meta = "FINGERPRINT")]
+    pub gpg_key: Vec<String>,
+
+    /// Clone the repository from this url, if the directory is missing or empty.
+    ///
+    /// After cloning, the actions run once for the initial deploy.
+    #[options(no_short, meta = "URL")]
+    pub clone: Option<String>,
+
+    /// The branch to follow (e.g. "release"). By default the checked out branch.
+    ///
+    /// When cloning, this branch is checked out instead of the default branch of the remote.
+    #[options(no_short, meta = "BRANCH")]
+    pub branch: Option<String>,
+
+    /// The remote to fetch the branch from (e.g. "upstream"). By default the upstream of the branch or origin.
+    #[options(no_short, meta = "REMOTE")]
+    pub remote: Option<String>,
+
+    /// Check out the commits in a detached HEAD, instead of updating the local branch. Needs --branch.
+    ///
+    /// With `--on tag` or `--on semver`, the tags are fetched from any branch and --branch is not needed.
+    #[options(no_short, long = "detached")]
+    pub detached: bool,
+
     /// Set the path for an ssh-key to be used when pulling.
     #[options(short = 'i', long = "ssh-key")]
     pub ssh_key: Option<String>,
@@ -93,10 +268,42 @@ pub struct Args {
     #[options(no_short, meta = "HOST")]
     pub git_known_host: Option<String>,
 
+    /// Triggers when this file or directory changes (e.g. a deploy marker), you can define multiple times. (Only supported on Linux)
+    #[options(no_short, meta = "PATH")]
+    pub trigger_file: Vec<String>,
+
+    /// Wait until the files stop changing for this long before triggering. By default 500ms.
+    #[options(no_short, meta = "DELAY")]
+    pub trigger_debounce: Option<DurationString>,
+
     /// Runs an HTTP server on the URL, which allows to trigger by calling it.
     #[options(no_short)]
     pub http: Option<String>,
 
+    /// Only trigger on this HTTP path (e.g. "/hooks/deploy"), you can define multiple times.
+    #[options(no_short, meta = "PATH")]
+    pub http_path: Vec<String>,
+
+    /// Only trigger on this HTTP method (e.g. "POST"), you can define multiple times.
+    #[options(no_short, meta = "METHOD")]
+    pub http_method: Vec<String>,
+
+    /// Serve HTTPS with this certificate in PEM format (reloaded on change).
+    #[options(no_short, meta = "FILE")]
+    pub http_tls_cert: Option<String>,
+
+    /// The private key in PEM format for the HTTPS certificate.
+    #[options(no_short, meta = "FILE")]
+    pub http_tls_key: Option<String>,
+
+    /// Wait for the check and actions to finish before responding to HTTP requests, and return the results.
+    #[options(no_short)]
+    pub http_sync: bool,
+
+    /// Only accept HTTP requests signed with this secret (GitHub, GitLab, Gitea or Forgejo webhooks).
+    #[options(no_short, meta = "SECRET")]
+    pub http_secret: Option<String>,
+
     /// The number of times to retry the background process in case it fails. By default 0 for no retries.
     #[options(no_short, meta = "N")]
     pub process_retries: Option<u32>,
@@ -126,20 +333,47 @@ pub struct Args {
     pub help: bool,
 }
 
+/// Print the updates saved to the state directory.
+#[derive(Debug, Options)]
+pub struct HistoryArgs {
+    /// The state directory, that was given to gw with --state-dir.
+    #[options(no_short, meta = "DIR")]
+    pub state_dir: Option<String>,
+
+    /// The number of updates to print, the newest last.
+    #[options(short = "n", meta = "N", default = "20")]
+    pub limit: usize,
+
+    /// Print the updates as JSON lines.
+    #[options(no_short)]
+    pub json: bool,
+
+    /// Print this help.
+    #[options()]
+    pub help: bool,
+}
+
 #[derive(Debug)]
 pub enum ArgAction {
     Process(String, bool),
     Script(String, bool),
+    WhenChanged(String),
 }
 
 pub fn parse_args() -> (Args, Vec<ArgAction>) {
     let args = Args::parse_args_default_or_exit();
 
     // We have to maintain positionality between different flags
+    let mut previous_arg = String::new();
     let arg_actions = env::args()
         .skip(2)
         .filter_map(|arg| {
-            if args.process.as_ref() == Some(&arg) {
+            let previous_arg = std::mem::replace(&mut previous_arg, arg.clone());
+            if let Some(glob) = arg.strip_prefix("--when-changed=") {
+                Some(ArgAction::WhenChanged(glob.to_string()))
+            } else if previous_arg == "--when-changed" && args.when_changed.contains(&arg) {
+                Some(ArgAction::WhenChanged(arg))
+            } else if args.process.as_ref() == Some(&arg) {
                 Some(ArgAction::Process(arg, false))
             } else if args.process_with_shell.as_ref() == Some(&arg) {
                 Some(ArgAction::Process(arg, true))
@@ -155,3 +389,32 @@ pub fn parse_args() -> (Args, Vec<ArgAction>) {
 
     (args, arg_actions)
 }
+
+/// Returns true if gw is called with the history subcommand (e.g. `gw history`).
+///
+/// It is only the subcommand, if `history` is followed by the history flags only, to watch
+/// a directory called `history`, call it as `./history`.
+pub fn is_history_command() -> bool {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.split_first() {
+        Some((command, flags)) if command == "history" => {
+            HistoryArgs::parse_args(flags, ParsingStyle::AllOptions).is_ok()
+        }
+        _ => false,
+    }
+}
+
+pub fn parse_history_args() -> HistoryArgs {
+    let args: Vec<String> = env::args().skip(2).collect();
+    match HistoryArgs::parse_args(&args, ParsingStyle::AllOptions) {
+        Ok(history_args) if history_args.help_requested() => {
+            println!("Usage: gw history [OPTIONS]\n\n{}", HistoryArgs::usage());
+            process::exit(0);
+        }
+        Ok(history_args) => history_args,
+        Err(err) => {
+            eprintln!("gw history: {err}");
+            process::exit(2);
+        }
+    }
+}
@@ -1,19 +1,34 @@
 use self::repository::GitRepository;
 use super::{Check, CheckError};
-use crate::context::Context;
-use std::fmt::{Debug, Display, Formatter};
+use crate::{context::Context, paths::PathFilter};
+use chrono::Utc;
+use git2::Oid;
+use std::{
+    fmt::{Debug, Display, Formatter},
+    path::Path,
+};
 use thiserror::Error;
 
 mod config;
 mod credentials;
 mod known_hosts;
 mod repository;
+mod signature;
+mod skip;
+mod version;
+mod window;
 
 use config::setup_gitconfig;
 pub use credentials::CredentialAuth;
 use known_hosts::setup_known_hosts;
-use log::warn;
+use log::{debug, info, warn};
 use repository::shorthash;
+pub use repository::{DirtyPolicy, GitTarget};
+pub use signature::SignatureVerifier;
+pub use skip::{SkipDirectives, SkipScope, SkipTrailer};
+use version::parse_version;
+pub use version::VersionRange;
+pub use window::{DeployWindow, DeployWindows};
 
 const CHECK_NAME: &str = "GIT";
 
@@ -21,6 +36,7 @@ const CHECK_NAME: &str = "GIT";
 pub enum GitTriggerArgument {
     Push,
     Tag(String),
+    Semver(VersionRange),
 }
 
 impl Display for GitTriggerArgument {
@@ -34,6 +50,7 @@ impl Display for GitTriggerArgument {
                     write!(f, "tag matching \"{pattern}\"")
                 }
             }
+            GitTriggerArgument::Semver(range) => write!(f, "version tag matching \"{range}\""),
         }
     }
 }
@@ -45,6 +62,14 @@ impl Display for GitTriggerArgument {
 pub struct GitCheck {
     pub repo: GitRepository,
     pub trigger: GitTriggerArgument,
+    windows: Option<DeployWindows>,
+    held_commit: Option<Oid>,
+    paths: PathFilter,
+    skip: SkipDirectives,
+    verifier: Option<SignatureVerifier>,
+    mirror: bool,
+    initial_deploy: bool,
+    failed_commit: Option<Oid>,
 }
 
 /// A custom error describing the error cases for the GitCheck.
@@ -73,6 +98,9 @@ pub enum GitError {
     /// Cannot create the ssh config
     #[error("cannot create ssh config")]
     SshConfigFailed,
+    /// Cannot clone the repository. This can be a network failure, authentication error or a missing branch.
+    #[error("cannot clone {0} ({1})")]
+    CloneFailed(String, String),
     /// Cannot fetch the current branch. This can be a network failure, authentication error or many other things.
     #[error("cannot fetch ({0})")]
     FetchFailed(String),
@@ -86,6 +114,30 @@ pub enum GitError {
     /// Cannot set the HEAD to the fetch commit.
     #[error("could not set HEAD to fetch commit {0}")]
     FailedSettingHead(String),
+    /// The timezone of the deploy windows is not in the IANA timezone database.
+    #[error("timezone {0} is invalid, use a name like Europe/Berlin")]
+    InvalidTimezone(String),
+    /// Cannot compare the commits to find the changed files.
+    #[error("failed listing the changed files ({0})")]
+    DiffFailed(String),
+    /// Cannot stash the local changes before updating.
+    #[error("cannot stash the local changes ({0})")]
+    StashFailed(String),
+    /// Cannot walk the history to find the pulled commits.
+    #[error("failed listing the pulled commits ({0})")]
+    LogFailed(String),
+    /// The allowed signers file for verifying SSH signatures doesn't exist.
+    #[error("allowed signers file {0} not found")]
+    AllowedSignersNotFound(String),
+    /// The GPG key is not a fingerprint or a long key ID.
+    #[error("GPG key {0:?} should be a fingerprint or a 16 digit key ID")]
+    InvalidGpgKey(String),
+    /// The commit to update to is not signed by an allowed key. It is not pulled.
+    #[error("refusing to update to {0}, because the signature cannot be verified: {1}")]
+    InvalidSignature(String, String),
+    /// Cannot check out the commit to another directory (e.g. a release directory).
+    #[error("cannot check out {0} to the release directory ({1})")]
+    ExportFailed(String, String),
 }
 
 impl From<GitError> for CheckError {
@@ -94,16 +146,24 @@ impl From<GitError> for CheckError {
             GitError::NotAGitRepository(_, _)
             | GitError::NoHead
             | GitError::NotOnABranch
-            | GitError::NoRemoteForBranch(_) => CheckError::Misconfigured(value.to_string()),
+            | GitError::NoRemoteForBranch(_)
+            | GitError::InvalidTimezone(_)
+            | GitError::AllowedSignersNotFound(_)
+            | GitError::InvalidGpgKey(_) => CheckError::Misconfigured(value.to_string()),
             GitError::ConfigLoadingFailed | GitError::SshConfigFailed => {
                 CheckError::PermissionDenied(value.to_string())
             }
-            GitError::DirtyWorkingTree | GitError::MergeConflict => {
-                CheckError::Conflict(value.to_string())
-            }
+            GitError::DirtyWorkingTree
+            | GitError::StashFailed(_)
+            | GitError::MergeConflict
+            | GitError::InvalidSignature(_, _) => CheckError::Conflict(value.to_string()),
             GitError::FetchFailed(_)
+            | GitError::CloneFailed(_, _)
             | GitError::FailedSettingHead(_)
-            | GitError::TagMatchingFailed => CheckError::FailedUpdate(value.to_string()),
+            | GitError::TagMatchingFailed
+            | GitError::DiffFailed(_)
+            | GitError::LogFailed(_)
+            | GitError::ExportFailed(_, _) => CheckError::FailedUpdate(value.to_string()),
         }
     }
 }
@@ -111,7 +171,16 @@ impl From<GitError> for CheckError {
 impl GitCheck {
     /// Open the git repository at the given directory.
     pub fn open_inner(directory: &str, trigger: GitTriggerArgument) -> Result<Self, CheckError> {
-        let repo = GitRepository::open(directory)?;
+        GitCheck::open_target_inner(directory, GitTarget::default(), trigger)
+    }
+
+    /// Open the git repository at the given directory, following the target branch.
+    pub fn open_target_inner(
+        directory: &str,
+        target: GitTarget,
+        trigger: GitTriggerArgument,
+    ) -> Result<Self, CheckError> {
+        let repo = GitRepository::open(directory, target)?;
 
         if let GitTriggerArgument::Tag(p) = &trigger {
             if !(p.contains('*') || p.contains('?') || p.contains('[') || p.contains('{')) {
@@ -119,11 +188,43 @@ impl GitCheck {
             }
         }
 
-        Ok(GitCheck { repo, trigger })
+        Ok(GitCheck {
+            repo,
+            trigger,
+            windows: None,
+            held_commit: None,
+            paths: PathFilter::default(),
+            skip: SkipDirectives::default(),
+            verifier: None,
+            mirror: false,
+            initial_deploy: false,
+            failed_commit: None,
+        })
     }
 
     pub fn open(
         directory: &str,
+        target: GitTarget,
+        additional_host: Option<String>,
+        trigger: GitTriggerArgument,
+    ) -> Result<Self, CheckError> {
+        let known_hosts_failed = setup_known_hosts(additional_host).is_err();
+        let gitconfig_failed = setup_gitconfig(directory).is_err();
+        if known_hosts_failed || gitconfig_failed {
+            warn!("Setting up known hosts or git configuration failed. Check if home directory exists and the permissions are correct.");
+        };
+
+        GitCheck::open_target_inner(directory, target, trigger)
+    }
+
+    /// Clone the repository from the url to the directory, and open it.
+    ///
+    /// The first check reports the cloned commit (or the matching tag) as an update, to run the initial deploy.
+    pub fn clone(
+        directory: &str,
+        url: &str,
+        target: GitTarget,
+        auth: Option<CredentialAuth>,
         additional_host: Option<String>,
         trigger: GitTriggerArgument,
     ) -> Result<Self, CheckError> {
@@ -133,15 +234,113 @@ impl GitCheck {
             warn!("Setting up known hosts or git configuration failed. Check if home directory exists and the permissions are correct.");
         };
 
-        GitCheck::open_inner(directory, trigger)
+        info!("Cloning {url} to {directory}.");
+        GitRepository::clone(url, directory, target.clone(), auth)?;
+
+        let mut git_check = GitCheck::open_target_inner(directory, target, trigger)?;
+        git_check.initial_deploy = true;
+
+        Ok(git_check)
     }
 
     pub fn set_auth(&mut self, auth: CredentialAuth) {
         self.repo.set_auth(auth);
     }
 
+    /// What to do with the local changes before updating. By default the update fails.
+    pub fn set_dirty_policy(&mut self, dirty_policy: DirtyPolicy) {
+        self.repo.set_dirty_policy(dirty_policy);
+    }
+
+    /// Ignore the local changes in these paths, they never block the update.
+    pub fn set_dirty_ignores(&mut self, dirty_ignores: PathFilter) {
+        self.repo.set_dirty_ignores(dirty_ignores);
+    }
+
+    /// Only allow updates in these windows, outside of them the updates are held back.
+    pub fn set_windows(&mut self, windows: DeployWindows) {
+        self.windows = Some(windows);
+    }
+
+    /// Only report an update, if the changed files match the filter. The pull happens regardless.
+    pub fn set_paths(&mut self, paths: PathFilter) {
+        self.paths = paths;
+    }
+
+    /// Pull the commits marked in the message (e.g. `[skip deploy]`) without reporting an update.
+    pub fn set_skip(&mut self, skip: SkipDirectives) {
+        self.skip = skip;
+    }
+
+    /// Only update to commits (or tags) signed by the allowed keys.
+    pub fn set_verifier(&mut self, verifier: SignatureVerifier) {
+        self.verifier = Some(verifier);
+    }
+
+    /// Reset to the remote if the branch diverged (e.g. after a force push), instead of failing.
+    pub fn set_mirror(&mut self, mirror: bool) {
+        self.mirror = mirror;
+    }
+
+    /// Run the actions on the checked out commit at the first check, even if there are no updates.
+    pub fn set_initial_deploy(&mut self, initial_deploy: bool) {
+        self.initial_deploy = initial_deploy;
+    }
+
+    fn rollback_inner(&mut self, context: &mut Context) -> Result<bool, GitError> {
+        let parse_commit = |key: &str| context.get(key).and_then(|sha| Oid::from_str(sha).ok());
+        let (Some(before_commit), Some(commit)) = (
+            parse_commit("GIT_BEFORE_COMMIT_SHA"),
+            parse_commit("GIT_COMMIT_SHA"),
+        ) else {
+            return Ok(false);
+        };
+        if before_commit == commit {
+            return Ok(false);
+        }
+
+        warn!(
+            "Rolling back from {} to {}.",
+            shorthash(&commit),
+            shorthash(&before_commit)
+        );
+        self.repo.roll_back(before_commit)?;
+        self.failed_commit = Some(commit);
+
+        context.insert("GIT_ROLLBACK_COMMIT_SHA", commit.to_string());
+        context.insert("GIT_ROLLBACK_COMMIT_SHORT_SHA", shorthash(&commit));
+        context.insert("GIT_COMMIT_SHA", before_commit.to_string());
+        context.insert("GIT_COMMIT_SHORT_SHA", shorthash(&before_commit));
+
+        Ok(true)
+    }
+
+    fn export_inner(&self, context: &Context, directory: &Path) -> Result<bool, GitError> {
+        let Some(commit) = context
+            .get("GIT_COMMIT_SHA")
+            .and_then(|sha| Oid::from_str(sha).ok())
+        else {
+            return Ok(false);
+        };
+
+        self.repo.export(commit, directory)?;
+
+        Ok(true)
+    }
+
     fn check_inner(&mut self, context: &mut Context) -> Result<bool, GitError> {
-        let GitCheck { repo, trigger } = self;
+        let GitCheck {
+            repo,
+            trigger,
+            windows,
+            held_commit,
+            paths,
+            skip,
+            verifier,
+            mirror,
+            initial_deploy,
+            failed_commit,
+        } = self;
 
         // Load context data from repository information
         let information = repo.get_repository_information()?;
@@ -152,39 +351,283 @@ impl GitCheck {
         context.insert("GIT_REMOTE_NAME", information.remote_name);
         context.insert("GIT_REMOTE_URL", information.remote_url);
 
-        // Pull repository contents and report
-        let fetch_commit = repo.fetch()?;
-        if repo.check_if_updatable(&fetch_commit)? {
-            match trigger {
-                GitTriggerArgument::Push => {
-                    repo.pull(fetch_commit.id())?;
-                    context.insert("GIT_REF_TYPE", "branch".to_string());
-                    context.insert("GIT_REF_NAME", information.ref_name);
-                    context.insert("GIT_COMMIT_SHA", fetch_commit.id().to_string());
-                    context.insert("GIT_COMMIT_SHORT_SHA", shorthash(&fetch_commit.id()));
-                    Ok(true)
+        // Find the commit to update to
+        let (commit, tag_name, force_update) = if *initial_deploy {
+            // Deploy the checked out commit (or the matching tag) once, e.g. after cloning
+            match select_initial_commit(repo, trigger, information.commit_sha)? {
+                Some((commit, tag_name)) => (commit, tag_name, false),
+                None => {
+                    info!("There are no tags matching the trigger, skipping the initial deploy.");
+                    *initial_deploy = false;
+                    return Ok(false);
+                }
+            }
+        } else if repo.is_detached() && !matches!(trigger, GitTriggerArgument::Push) {
+            // Follow the tags on any branch, and check out the newest matching one
+            repo.fetch_tags()?;
+            let selected = match trigger {
+                GitTriggerArgument::Semver(range) => {
+                    let tags = repo
+                        .find_all_tags("*")?
+                        .into_iter()
+                        .map(|(tag_name, commit, _)| (tag_name, commit));
+                    select_version(repo, range, information.commit_sha, tags)?
+                }
+                GitTriggerArgument::Tag(pattern) => repo
+                    .find_all_tags(pattern)?
+                    .into_iter()
+                    .max_by(|(a_name, _, a_time), (b_name, _, b_time)| {
+                        a_time.cmp(b_time).then(a_name.cmp(b_name))
+                    })
+                    .map(|(tag_name, commit, _)| (tag_name, commit)),
+                GitTriggerArgument::Push => None,
+            };
+            match selected {
+                Some((tag_name, commit)) if commit != information.commit_sha => {
+                    (commit, Some(tag_name), false)
+                }
+                _ => return Ok(false),
+            }
+        } else {
+            let (fetch_commit_id, force_update) = {
+                let fetch_commit = repo.fetch()?;
+                match repo.check_if_updatable(&fetch_commit) {
+                    Ok(true) => (fetch_commit.id(), false),
+                    // In mirror mode, the remote can also be force-pushed back to an older commit
+                    Ok(false) if *mirror && fetch_commit.id() != information.commit_sha => {
+                        (fetch_commit.id(), true)
+                    }
+                    Ok(false) => return Ok(false),
+                    Err(GitError::MergeConflict) if *mirror => (fetch_commit.id(), true),
+                    Err(err) => return Err(err),
                 }
+            };
+            match trigger {
+                GitTriggerArgument::Push => (fetch_commit_id, None, force_update),
                 GitTriggerArgument::Tag(pattern) => {
-                    let mut tags = repo.find_tags(fetch_commit.id(), pattern)?;
-                    if let Some((tag_name, commit)) = tags.pop() {
-                        repo.pull(commit)?;
-                        context.insert("GIT_REF_TYPE", "tag".to_string());
-                        context.insert("GIT_REF_NAME", format!("refs/tags/{tag_name}"));
-                        context.insert("GIT_COMMIT_SHA", commit.to_string());
-                        context.insert("GIT_COMMIT_SHORT_SHA", shorthash(&commit));
-                        context.insert("GIT_COMMIT_TAG_NAME", tag_name.to_string());
-                        Ok(true)
-                    } else {
-                        Ok(false)
+                    match repo.find_tags(fetch_commit_id, pattern)?.pop() {
+                        Some((tag_name, commit)) => (commit, Some(tag_name), force_update),
+                        None => return Ok(false),
+                    }
+                }
+                GitTriggerArgument::Semver(range) => {
+                    let tags = repo.find_tags(fetch_commit_id, "*")?;
+                    match select_version(repo, range, information.commit_sha, tags)? {
+                        Some((tag_name, commit)) => (commit, Some(tag_name), force_update),
+                        None => return Ok(false),
                     }
                 }
             }
+        };
+
+        // Don't retry a rolled back commit, until a newer one arrives
+        if *failed_commit == Some(commit) {
+            debug!(
+                "Update to {} was rolled back, waiting for a newer commit.",
+                shorthash(&commit)
+            );
+            return Ok(false);
+        }
+
+        // Verify the signature before touching the working tree
+        if let Some(verifier) = verifier {
+            verifier.verify(repo, commit, tag_name.as_deref())?;
+        }
+
+        // Hold back the update, if we are outside of the deploy windows
+        if let Some(windows) = windows {
+            if !windows.is_open(Utc::now()) {
+                if *held_commit != Some(commit) {
+                    info!(
+                        "Holding update to {} until the deploy window opens ({windows}).",
+                        shorthash(&commit)
+                    );
+                    *held_commit = Some(commit);
+                } else {
+                    debug!("Update to {} is still held.", shorthash(&commit));
+                }
+                context.insert("GIT_HELD_COMMIT_SHA", commit.to_string());
+                context.insert("GIT_HELD_COMMIT_SHORT_SHA", shorthash(&commit));
+                return Ok(false);
+            }
+        }
+        if held_commit.take().is_some() {
+            info!("Deploy window is open, updating to {}.", shorthash(&commit));
+        }
+
+        // Pull repository contents and report
+        if commit == information.commit_sha {
+            debug!("Commit {} is already checked out.", shorthash(&commit));
+        } else if force_update {
+            warn!(
+                "The branch cannot be fast-forwarded to the remote, resetting from {} to {}.",
+                shorthash(&information.commit_sha),
+                shorthash(&commit)
+            );
+            repo.reset(commit)?;
+        } else {
+            repo.pull(commit)?;
+        }
+        context.insert("GIT_FORCE_UPDATED", force_update.to_string());
+        context.insert("GIT_COMMIT_SHA", commit.to_string());
+        context.insert("GIT_COMMIT_SHORT_SHA", shorthash(&commit));
+        if let Some(tag_name) = tag_name {
+            context.insert("GIT_REF_TYPE", "tag".to_string());
+            context.insert("GIT_REF_NAME", format!("refs/tags/{tag_name}"));
+            if let Some(version) = parse_version(&tag_name) {
+                context.insert("GIT_COMMIT_TAG_MAJOR", version.major.to_string());
+                context.insert("GIT_COMMIT_TAG_MINOR", version.minor.to_string());
+                context.insert("GIT_COMMIT_TAG_PATCH", version.patch.to_string());
+                context.insert("GIT_COMMIT_TAG_PRERELEASE", version.pre.to_string());
+            }
+            context.insert("GIT_COMMIT_TAG_NAME", tag_name);
         } else {
-            Ok(false)
+            context.insert("GIT_REF_TYPE", "branch".to_string());
+            context.insert("GIT_REF_NAME", information.ref_name);
+        }
+
+        // The initial deploy runs regardless of the pulled commits
+        if *initial_deploy {
+            info!("Running the initial deploy of {}.", shorthash(&commit));
+            *initial_deploy = false;
+            return Ok(true);
+        }
+
+        // Add the information about the pulled commits
+        let commits = repo.find_commits(information.commit_sha, commit)?;
+        context.insert("GIT_COMMIT_COUNT", commits.len().to_string());
+        context.insert(
+            "GIT_COMMIT_LOG",
+            commits
+                .iter()
+                .map(|c| format!("{} {}", shorthash(&c.commit_sha), c.summary))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        if let Some(latest) = commits.iter().find(|c| c.commit_sha == commit) {
+            context.insert("GIT_COMMIT_MESSAGE", latest.message.clone());
+            context.insert("GIT_COMMIT_AUTHOR_NAME", latest.author_name.clone());
+            context.insert("GIT_COMMIT_AUTHOR_EMAIL", latest.author_email.clone());
+            if let Some(time) = latest.time {
+                context.insert("GIT_COMMIT_TIMESTAMP", time.to_rfc3339());
+            }
+            context.insert(
+                "GIT_COMMIT_TRAILERS",
+                latest
+                    .trailers
+                    .iter()
+                    .map(|(key, value)| format!("{key}: {value}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        }
+
+        // Skip the deploy if the commits are marked
+        if let Some(reason) = skip.find_skip_reason(&commits) {
+            info!(
+                "Updated to {}, but skipping the deploy because of {reason:?}.",
+                shorthash(&commit)
+            );
+            return Ok(false);
+        }
+
+        // Check if the update changed any relevant files
+        let changed_files = repo.find_changed_files(information.commit_sha, commit)?;
+        context.insert("GIT_CHANGED_FILES", changed_files.join("\n"));
+        if !paths.is_empty() {
+            let matched_files = paths.filter(&changed_files);
+            if matched_files.is_empty() {
+                info!(
+                    "Updated to {}, but none of the {} changed files match the paths.",
+                    shorthash(&commit),
+                    changed_files.len()
+                );
+                return Ok(false);
+            }
+            context.insert(
+                "GIT_MATCHED_FILES",
+                matched_files
+                    .iter()
+                    .map(|f| f.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
         }
+
+        Ok(true)
     }
 }
 
+/// Select the commit of the initial deploy, which is the checked out commit or on tag triggers the matching tag.
+///
+/// On a branch, only the tags of the checked out commit and its ancestors are considered.
+fn select_initial_commit(
+    repo: &GitRepository,
+    trigger: &GitTriggerArgument,
+    current_commit: Oid,
+) -> Result<Option<(Oid, Option<String>)>, GitError> {
+    let pattern = match trigger {
+        GitTriggerArgument::Push => return Ok(Some((current_commit, None))),
+        GitTriggerArgument::Tag(pattern) => pattern.as_str(),
+        GitTriggerArgument::Semver(_) => "*",
+    };
+
+    let tags = if repo.is_detached() {
+        repo.fetch_tags()?;
+        repo.find_all_tags(pattern)?
+    } else {
+        let reachable_tags = repo.find_reachable_tags(current_commit)?;
+        repo.find_all_tags(pattern)?
+            .into_iter()
+            .filter(|(tag_name, _, _)| reachable_tags.contains(tag_name))
+            .collect()
+    };
+
+    let selected = match trigger {
+        GitTriggerArgument::Semver(range) => range
+            .select(
+                tags.into_iter()
+                    .map(|(tag_name, commit, _)| (tag_name, commit)),
+                None,
+            )
+            .map(|(tag_name, _, commit)| (tag_name, commit)),
+        _ => tags
+            .into_iter()
+            .max_by(|(a_name, _, a_time), (b_name, _, b_time)| {
+                a_time.cmp(b_time).then(a_name.cmp(b_name))
+            })
+            .map(|(tag_name, commit, _)| (tag_name, commit)),
+    };
+
+    Ok(selected.map(|(tag_name, commit)| (commit, Some(tag_name))))
+}
+
+/// Select the highest version in the range from the tags.
+///
+/// It never goes back to a lower version than the deployed one (the tags of the current commit and its ancestors).
+fn select_version(
+    repo: &GitRepository,
+    range: &VersionRange,
+    current_commit: Oid,
+    tags: impl IntoIterator<Item = (String, Oid)>,
+) -> Result<Option<(String, Oid)>, GitError> {
+    let current = repo
+        .find_reachable_tags(current_commit)?
+        .iter()
+        .filter_map(|tag_name| parse_version(tag_name))
+        .max();
+
+    Ok(range
+        .select(tags, current.as_ref())
+        .map(|(tag_name, version, commit)| {
+            debug!(
+                "Selected version {version} from tag {tag_name} (deployed: {}).",
+                current.map_or(String::from("none"), |v| v.to_string())
+            );
+            (tag_name, commit)
+        }))
+}
+
 impl Check for GitCheck {
     /// Fetch and pull changes from the remote repository on the current branch.
     /// It returns true if the pull was successful and there are new changes.
@@ -193,14 +636,29 @@ impl Check for GitCheck {
 
         Ok(update_successful)
     }
+
+    /// Reset to the commit before the update, and don't update to the failed commit again.
+    fn rollback(&mut self, context: &mut Context) -> Result<bool, CheckError> {
+        let rolled_back = self.rollback_inner(context)?;
+
+        Ok(rolled_back)
+    }
+
+    /// Check out the updated commit to the directory, without changing the repository.
+    fn export(&mut self, context: &Context, directory: &Path) -> Result<bool, CheckError> {
+        let exported = self.export_inner(context, directory)?;
+
+        Ok(exported)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::{DateTime, Datelike};
     use duct::cmd;
     use rand::distr::{Alphanumeric, SampleString};
-    use std::{collections::HashMap, error::Error, fs, path::Path};
+    use std::{collections::HashMap, error::Error, fs, path::Path, slice};
 
     fn get_random_id() -> String {
         Alphanumeric.sample_string(&mut rand::rng(), 16)
@@ -315,6 +773,98 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn it_should_clone_a_repository_and_deploy_once() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        let remote = format!("{local}-remote");
+        let other = format!("{local}-other");
+
+        create_empty_repository(&local)?;
+
+        let mut check = GitCheck::clone(
+            &other,
+            &remote,
+            GitTarget {
+                branch: Some(String::from("master")),
+                ..Default::default()
+            },
+            None,
+            None,
+            GitTriggerArgument::Push,
+        )?;
+
+        // The first check should deploy the cloned commit
+        let mut context: Context = HashMap::new();
+        assert!(check.check_inner(&mut context)?);
+        assert_eq!(get_last_commit(&local)?, context["GIT_COMMIT_SHA"]);
+        assert_eq!("1", fs::read_to_string(format!("{other}/1"))?);
+
+        // The next checks should only report updates
+        let mut context: Context = HashMap::new();
+        assert!(!check.check_inner(&mut context)?);
+
+        cleanup_repository(&local)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_deploy_the_matching_tag_once_after_cloning() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        let remote = format!("{local}-remote");
+        let other = format!("{local}-other");
+
+        create_empty_repository(&local)?;
+        create_tag(&local, "v0.1.0")?;
+        let tagged_commit_sha = get_last_commit(&local)?;
+        create_commit(&local, "2", "2")?;
+        push_all(&local)?;
+
+        let mut check = GitCheck::clone(
+            &other,
+            &remote,
+            GitTarget {
+                branch: Some(String::from("master")),
+                ..Default::default()
+            },
+            None,
+            None,
+            GitTriggerArgument::Tag(String::from("v*")),
+        )?;
+
+        // Only allow deploying on tomorrow, so the window is closed
+        let tomorrow = Utc::now().weekday().succ();
+        let closed_window = DeployWindows::new(
+            vec![format!("{tomorrow} 00:00-24:00").parse()?],
+            Some("UTC"),
+        )?;
+        check.set_windows(closed_window);
+        let mut context: Context = HashMap::new();
+        assert!(!check.check_inner(&mut context)?);
+        assert_eq!(tagged_commit_sha, context["GIT_HELD_COMMIT_SHA"]);
+
+        // After the window opens, the tag should be checked out and deployed, not the branch
+        let open_window = DeployWindows::new(vec!["00:00-24:00".parse()?], Some("UTC"))?;
+        check.set_windows(open_window);
+        let mut context: Context = HashMap::new();
+        assert!(check.check_inner(&mut context)?);
+        assert_eq!(tagged_commit_sha, context["GIT_COMMIT_SHA"]);
+        assert_eq!("tag", context["GIT_REF_TYPE"]);
+        assert_eq!("refs/tags/v0.1.0", context["GIT_REF_NAME"]);
+        assert_eq!(tagged_commit_sha, get_last_commit(&other)?);
+        assert!(!Path::new(&format!("{other}/2")).exists());
+
+        // The next checks should only report updates
+        let mut context: Context = HashMap::new();
+        assert!(!check.check_inner(&mut context)?);
+
+        cleanup_repository(&local)?;
+
+        Ok(())
+    }
+
     #[test]
     fn it_should_fail_if_path_is_invalid() -> Result<(), Box<dyn Error>> {
         let error = GitCheck::open_inner("/path/to/nowhere", GitTriggerArgument::Push)
@@ -435,6 +985,28 @@ mod tests {
                 .unwrap()
         );
 
+        assert_eq!("false", context.get("GIT_FORCE_UPDATED").unwrap());
+
+        // It should add the pulled commits
+        let author = cmd!("git", "log", "-1", "--format=%an <%ae>")
+            .dir(&local)
+            .read()?;
+        assert_eq!("1", context.get("GIT_COMMIT_COUNT").unwrap());
+        assert_eq!("1", context.get("GIT_COMMIT_MESSAGE").unwrap());
+        assert_eq!(
+            &format!("{} 1", &commit_sha[0..7]),
+            context.get("GIT_COMMIT_LOG").unwrap()
+        );
+        assert_eq!(
+            author,
+            format!(
+                "{} <{}>",
+                context.get("GIT_COMMIT_AUTHOR_NAME").unwrap(),
+                context.get("GIT_COMMIT_AUTHOR_EMAIL").unwrap()
+            )
+        );
+        assert!(DateTime::parse_from_rfc3339(context.get("GIT_COMMIT_TIMESTAMP").unwrap()).is_ok());
+
         let _ = cleanup_repository(&local);
 
         Ok(())
@@ -565,30 +1137,136 @@ mod tests {
         Ok(())
     }
 
+    fn create_version_tags(local: &str) -> Result<(), Box<dyn Error>> {
+        let other = format!("{local}-other");
+
+        create_tag(local, "v2.0.0")?;
+        create_other_repository(local)?;
+        create_tag(&other, "v2.1.0")?;
+        create_commit(&other, "3", "3")?;
+        create_tag(&other, "v2.2.0-rc.1")?;
+        create_commit(&other, "4", "4")?;
+        create_tag(&other, "v3.0.0")?;
+
+        Ok(())
+    }
+
     #[test]
-    fn it_should_fail_if_the_working_tree_is_dirty() -> Result<(), Box<dyn Error>> {
+    fn it_should_update_to_the_highest_version_in_the_range() -> Result<(), Box<dyn Error>> {
         let id = get_random_id();
         let local = format!("test_directories/{id}");
 
         create_empty_repository(&local)?;
+        create_version_tags(&local)?;
 
-        // Create another repository and push a new commit
+        let range: VersionRange = "^2".parse()?;
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Semver(range))?;
+        let mut context: Context = HashMap::new();
+        assert!(check.check_inner(&mut context)?);
+
+        // The pre-release and the next major version should not be pulled
+        assert!(Path::new(&format!("{local}/2")).exists());
+        assert!(!Path::new(&format!("{local}/3")).exists());
+        assert_eq!("v2.1.0", context.get("GIT_COMMIT_TAG_NAME").unwrap());
+        assert_eq!("2", context.get("GIT_COMMIT_TAG_MAJOR").unwrap());
+        assert_eq!("1", context.get("GIT_COMMIT_TAG_MINOR").unwrap());
+        assert_eq!("0", context.get("GIT_COMMIT_TAG_PATCH").unwrap());
+        assert_eq!("", context.get("GIT_COMMIT_TAG_PRERELEASE").unwrap());
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_update_to_prereleases_if_allowed() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+        create_version_tags(&local)?;
+
+        let mut range: VersionRange = "^2".parse()?;
+        range.set_prerelease(true);
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Semver(range))?;
+        let mut context: Context = HashMap::new();
+        assert!(check.check_inner(&mut context)?);
+
+        assert!(Path::new(&format!("{local}/3")).exists());
+        assert!(!Path::new(&format!("{local}/4")).exists());
+        assert_eq!("v2.2.0-rc.1", context.get("GIT_COMMIT_TAG_NAME").unwrap());
+        assert_eq!("rc.1", context.get("GIT_COMMIT_TAG_PRERELEASE").unwrap());
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_not_update_to_a_lower_version() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+        create_tag(&local, "v2.1.0")?;
+
+        // Push a backported fix with a lower version
         create_other_repository(&local)?;
+        create_tag(&format!("{local}-other"), "v2.0.5")?;
 
-        // Add uncommited modification to emulate a dirty working tree
-        fs::write(format!("{local}/1"), "22")?;
+        let range: VersionRange = "^2".parse()?;
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Semver(range))?;
+        let mut context: Context = HashMap::new();
+        assert!(!check.check_inner(&mut context)?);
+        assert!(!Path::new(&format!("{local}/2")).exists());
 
-        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_update_to_tags_on_other_branches() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        let other = format!("{local}-other");
+
+        create_empty_repository(&local)?;
+
+        // Tag a commit on a release branch, that is not merged
+        cmd!("git", "clone", format!("{local}-remote"), &other).read()?;
+        cmd!("git", "checkout", "-b", "release/1.0")
+            .dir(&other)
+            .read()?;
+        create_commit(&other, "2", "2")?;
+        cmd!("git", "tag", "v1.0.0").dir(&other).read()?;
+        cmd!("git", "push", "origin", "release/1.0")
+            .dir(&other)
+            .read()?;
+        cmd!("git", "push", "--tags").dir(&other).read()?;
+
+        let target = GitTarget {
+            detached: true,
+            ..Default::default()
+        };
+        let mut check =
+            GitCheck::open_target_inner(&local, target, GitTriggerArgument::Tag("v*".to_string()))?;
         let mut context: Context = HashMap::new();
-        let error = check.check_inner(&mut context).err().unwrap();
+        assert!(check.check_inner(&mut context)?);
 
-        assert!(
-            matches!(error, GitError::DirtyWorkingTree),
-            "{error:?} should be DirtyWorkingTree"
+        // The tag should be checked out in a detached HEAD
+        assert!(Path::new(&format!("{local}/2")).exists());
+        assert_eq!(get_last_commit(&other)?, get_last_commit(&local)?);
+        assert_eq!("tag", context.get("GIT_REF_TYPE").unwrap());
+        assert_eq!("v1.0.0", context.get("GIT_COMMIT_TAG_NAME").unwrap());
+        assert_eq!(
+            "",
+            cmd!("git", "branch", "--show-current").dir(&local).read()?
         );
 
-        // The pushed file should be pulled
-        assert!(!Path::new(&format!("{local}/2")).exists());
+        // It should not update again to the same tag
+        let mut context: Context = HashMap::new();
+        assert!(!check.check_inner(&mut context)?);
 
         let _ = cleanup_repository(&local);
 
@@ -596,64 +1274,822 @@ mod tests {
     }
 
     #[test]
-    fn it_should_fail_if_there_is_a_merge_conflict() -> Result<(), Box<dyn Error>> {
+    fn it_should_roll_back_and_wait_for_a_newer_commit() -> Result<(), Box<dyn Error>> {
         let id = get_random_id();
         let local = format!("test_directories/{id}");
+        let other = format!("{local}-other");
 
         create_empty_repository(&local)?;
+        let before_commit_sha = get_last_commit(&local)?;
 
         // Create another repository and push a new commit
         create_other_repository(&local)?;
-
-        // Modify the same file in both directories to create a merge conflict
-        create_merge_conflict(&local)?;
+        let failed_commit_sha = get_last_commit(&other)?;
 
         let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
         let mut context: Context = HashMap::new();
-        let error = check.check_inner(&mut context).err().unwrap();
+        assert!(check.check_inner(&mut context)?);
 
-        assert!(
-            matches!(error, GitError::MergeConflict),
-            "{error:?} should be MergeConflict"
+        // Rolling back should reset to the previous commit
+        assert!(check.rollback_inner(&mut context)?);
+        assert_eq!(before_commit_sha, get_last_commit(&local)?);
+        assert!(!Path::new(&format!("{local}/2")).exists());
+        assert_eq!(&before_commit_sha, context.get("GIT_COMMIT_SHA").unwrap());
+        assert_eq!(
+            &failed_commit_sha,
+            context.get("GIT_ROLLBACK_COMMIT_SHA").unwrap()
         );
 
+        // The failed commit should not be pulled again
+        let mut context: Context = HashMap::new();
+        assert!(!check.check_inner(&mut context)?);
+        assert_eq!(before_commit_sha, get_last_commit(&local)?);
+
+        // A newer commit should be pulled
+        create_commit(&other, "3", "3")?;
+        push_all(&other)?;
+        let mut context: Context = HashMap::new();
+        assert!(check.check_inner(&mut context)?);
+        assert!(Path::new(&format!("{local}/3")).exists());
+
         let _ = cleanup_repository(&local);
 
         Ok(())
     }
 
     #[test]
-    #[cfg(unix)]
-    fn it_should_fail_if_repository_is_not_accessible() -> Result<(), Box<dyn Error>> {
+    fn it_should_roll_back_with_untracked_files() -> Result<(), Box<dyn Error>> {
         let id = get_random_id();
         let local = format!("test_directories/{id}");
 
         create_empty_repository(&local)?;
+        let before_commit_sha = get_last_commit(&local)?;
 
         // Create another repository and push a new commit
         create_other_repository(&local)?;
 
-        // Set repository to readonly
-        let mut perms = fs::metadata(&local)?.permissions();
-        perms.set_readonly(true);
-        fs::set_permissions(&local, perms)?;
-
-        let mut check: GitCheck = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        // Fail on every local change, the untracked files still shouldn't block the rollback
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        check.set_dirty_policy(DirtyPolicy::Fail);
         let mut context: Context = HashMap::new();
-        let error = check.check_inner(&mut context).err().unwrap();
+        assert!(check.check_inner(&mut context)?);
 
-        assert!(
-            matches!(error, GitError::FailedSettingHead(_)),
-            "{error:?} should be FailedSettingHead"
-        );
+        // Add an untracked output of the failed build
+        fs::write(format!("{local}/build.log"), "failed")?;
 
-        // Set repository to readonly
-        let mut perms = fs::metadata(&local)?.permissions();
-        #[allow(clippy::permissions_set_readonly_false)]
-        perms.set_readonly(false);
-        fs::set_permissions(&local, perms)?;
+        assert!(check.rollback_inner(&mut context)?);
+        assert_eq!(before_commit_sha, get_last_commit(&local)?);
+        assert!(!Path::new(&format!("{local}/2")).exists());
+        assert_eq!("failed", fs::read_to_string(format!("{local}/build.log"))?);
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_export_the_update_to_a_directory() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        let release = format!("{local}-release");
+
+        create_empty_repository(&local)?;
+        create_other_repository(&local)?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        let mut context: Context = HashMap::new();
+        assert!(check.check_inner(&mut context)?);
+
+        // The files of the commit should be written, without the repository
+        fs::create_dir_all(&release)?;
+        assert!(check.export_inner(&context, Path::new(&release))?);
+        assert_eq!("2", fs::read_to_string(format!("{release}/2"))?);
+        assert!(Path::new(&format!("{release}/1")).exists());
+        assert!(!Path::new(&format!("{release}/.git")).exists());
+        assert_eq!("", cmd!("git", "status", "--porcelain").dir(&local).read()?);
+
+        let _ = fs::remove_dir_all(&release);
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_follow_the_configured_branch() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        let other = format!("{local}-other");
+
+        create_empty_repository(&local)?;
+
+        // Push a new commit to the release branch
+        cmd!("git", "clone", format!("{local}-remote"), &other).read()?;
+        cmd!("git", "checkout", "-b", "release")
+            .dir(&other)
+            .read()?;
+        create_commit(&other, "2", "2")?;
+        cmd!("git", "push", "origin", "release")
+            .dir(&other)
+            .read()?;
+
+        let target = GitTarget {
+            remote: Some(String::from("origin")),
+            branch: Some(String::from("release")),
+            detached: false,
+        };
+        let mut check = GitCheck::open_target_inner(&local, target, GitTriggerArgument::Push)?;
+        let mut context: Context = HashMap::new();
+        assert!(check.check_inner(&mut context)?);
+
+        // The release branch should be created and checked out
+        assert!(Path::new(&format!("{local}/2")).exists());
+        assert_eq!("refs/heads/release", context.get("GIT_REF_NAME").unwrap());
+        assert_eq!(
+            "release",
+            cmd!("git", "branch", "--show-current").dir(&local).read()?
+        );
+        assert_eq!(get_last_commit(&other)?, get_last_commit(&local)?);
+
+        cleanup_repository(&local)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_update_in_a_detached_head() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+        let before_commit_sha = get_last_commit(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        let target = GitTarget {
+            remote: None,
+            branch: Some(String::from("master")),
+            detached: true,
+        };
+        let mut check =
+            GitCheck::open_target_inner(&local, target.clone(), GitTriggerArgument::Push)?;
+        let mut context: Context = HashMap::new();
+        assert!(check.check_inner(&mut context)?);
+
+        // The commit should be checked out, without moving the local branch
+        assert!(Path::new(&format!("{local}/2")).exists());
+        assert_eq!(
+            context.get("GIT_COMMIT_SHA").unwrap(),
+            &get_last_commit(&local)?
+        );
+        assert_eq!(
+            "",
+            cmd!("git", "branch", "--show-current").dir(&local).read()?
+        );
+        assert_eq!(
+            before_commit_sha,
+            cmd!("git", "rev-parse", "master").dir(&local).read()?
+        );
+
+        // It should open the detached repository again
+        let mut check = GitCheck::open_target_inner(&local, target, GitTriggerArgument::Push)?;
+        let mut context: Context = HashMap::new();
+        assert!(!check.check_inner(&mut context)?);
+
+        cleanup_repository(&local)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fail_if_the_working_tree_is_dirty() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        // Add uncommited modification to emulate a dirty working tree
+        fs::write(format!("{local}/1"), "22")?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        let mut context: Context = HashMap::new();
+        let error = check.check_inner(&mut context).err().unwrap();
+
+        assert!(
+            matches!(error, GitError::DirtyWorkingTree),
+            "{error:?} should be DirtyWorkingTree"
+        );
+
+        // The pushed file should be pulled
+        assert!(!Path::new(&format!("{local}/2")).exists());
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_pull_with_untracked_files_by_default() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        // Add an untracked build output
+        fs::create_dir(format!("{local}/target"))?;
+        fs::write(format!("{local}/target/app"), "app")?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        let mut context: Context = HashMap::new();
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(is_pulled);
+
+        // The untracked file should be kept
+        assert!(Path::new(&format!("{local}/2")).exists());
+        assert_eq!("app", fs::read_to_string(format!("{local}/target/app"))?);
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_ignore_untracked_and_ignored_paths() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        // Add an untracked file and a modification in an ignored path
+        fs::create_dir(format!("{local}/uploads"))?;
+        fs::write(format!("{local}/uploads/image.png"), "image")?;
+        fs::write(format!("{local}/1"), "11")?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        check.set_dirty_policy(DirtyPolicy::IgnoreUntracked);
+        let mut context: Context = HashMap::new();
+        let error = check.check_inner(&mut context).err().unwrap();
+        assert!(
+            matches!(error, GitError::DirtyWorkingTree),
+            "{error:?} should be DirtyWorkingTree"
+        );
+
+        check.set_dirty_ignores(PathFilter::new(&[String::from("/1")], &[])?);
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(is_pulled);
+
+        // The local changes should be kept
+        assert!(Path::new(&format!("{local}/2")).exists());
+        assert_eq!(
+            "image",
+            fs::read_to_string(format!("{local}/uploads/image.png"))?
+        );
+        assert_eq!("11", fs::read_to_string(format!("{local}/1"))?);
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_stash_and_reapply_local_changes() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        // Add uncommited modification to emulate a dirty working tree
+        fs::write(format!("{local}/1"), "11")?;
+        fs::write(format!("{local}/3"), "3")?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        check.set_dirty_policy(DirtyPolicy::Stash);
+        let mut context: Context = HashMap::new();
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(is_pulled);
+
+        // The update should be pulled, and the local changes reapplied
+        assert!(Path::new(&format!("{local}/2")).exists());
+        assert_eq!("11", fs::read_to_string(format!("{local}/1"))?);
+        assert_eq!("3", fs::read_to_string(format!("{local}/3"))?);
+        assert_eq!("", cmd!("git", "stash", "list").dir(&local).read()?);
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_not_stash_ignored_local_changes() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        // Add a modification and an untracked file in an ignored path
+        fs::write(format!("{local}/1"), "11")?;
+        fs::create_dir(format!("{local}/uploads"))?;
+        fs::write(format!("{local}/uploads/image.png"), "image")?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        check.set_dirty_policy(DirtyPolicy::Stash);
+        check.set_dirty_ignores(PathFilter::new(&[String::from("uploads/")], &[])?);
+        let mut context: Context = HashMap::new();
+        let error = check.check_inner(&mut context).err().unwrap();
+        assert!(
+            matches!(error, GitError::StashFailed(_)),
+            "{error:?} should be StashFailed"
+        );
+
+        // Nothing should be stashed or pulled
+        assert_eq!("", cmd!("git", "stash", "list").dir(&local).read()?);
+        assert!(!Path::new(&format!("{local}/2")).exists());
+        assert_eq!("11", fs::read_to_string(format!("{local}/1"))?);
+        assert_eq!(
+            "image",
+            fs::read_to_string(format!("{local}/uploads/image.png"))?
+        );
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_discard_local_changes() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        // Add uncommited modification to emulate a dirty working tree
+        fs::write(format!("{local}/1"), "11")?;
+        fs::write(format!("{local}/3"), "3")?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        check.set_dirty_policy(DirtyPolicy::Discard);
+        let mut context: Context = HashMap::new();
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(is_pulled);
+
+        // The update should be pulled, and the local changes gone
+        assert!(Path::new(&format!("{local}/2")).exists());
+        assert_eq!("1", fs::read_to_string(format!("{local}/1"))?);
+        assert!(!Path::new(&format!("{local}/3")).exists());
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fail_if_there_is_a_merge_conflict() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        // Modify the same file in both directories to create a merge conflict
+        create_merge_conflict(&local)?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        let mut context: Context = HashMap::new();
+        let error = check.check_inner(&mut context).err().unwrap();
+
+        assert!(
+            matches!(error, GitError::MergeConflict),
+            "{error:?} should be MergeConflict"
+        );
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_reset_diverged_branches_in_mirror_mode() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        // Commit locally, so the branch diverges from the remote
+        create_merge_conflict(&local)?;
+        let before_commit_sha = get_last_commit(&local)?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        check.set_mirror(true);
+        let mut context: Context = HashMap::new();
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(is_pulled);
+
+        // The local commit should be gone and the remote pulled
+        assert_eq!("1", fs::read_to_string(format!("{local}/1"))?);
+        assert!(Path::new(&format!("{local}/2")).exists());
+        assert_ne!(before_commit_sha, get_last_commit(&local)?);
+        assert_eq!(
+            &before_commit_sha,
+            context.get("GIT_BEFORE_COMMIT_SHA").unwrap()
+        );
+        assert_eq!("true", context.get("GIT_FORCE_UPDATED").unwrap());
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_reset_rewound_branches_in_mirror_mode() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        let other = format!("{local}-other");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        check.set_mirror(true);
+        let mut context: Context = HashMap::new();
+        assert!(check.check_inner(&mut context)?);
+        let before_commit_sha = get_last_commit(&local)?;
+
+        // Force-push the remote back to the first commit
+        cmd!("git", "reset", "--hard", "HEAD~1")
+            .dir(&other)
+            .read()?;
+        cmd!("git", "push", "--force", "origin", "master")
+            .dir(&other)
+            .read()?;
+
+        let mut context: Context = HashMap::new();
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(is_pulled);
+
+        // The rewound commit should be gone
+        assert!(!Path::new(&format!("{local}/2")).exists());
+        assert_eq!(get_last_commit(&other)?, get_last_commit(&local)?);
+        assert_eq!(
+            &before_commit_sha,
+            context.get("GIT_BEFORE_COMMIT_SHA").unwrap()
+        );
+        assert_eq!("true", context.get("GIT_FORCE_UPDATED").unwrap());
+
+        // Without mirror mode, a remote behind the local branch should be ignored
+        create_commit(&local, "3", "3")?;
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        let mut context: Context = HashMap::new();
+        assert!(!check.check_inner(&mut context)?);
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn it_should_fail_if_repository_is_not_accessible() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        // Set repository to readonly
+        let mut perms = fs::metadata(&local)?.permissions();
+        perms.set_readonly(true);
+        fs::set_permissions(&local, perms)?;
+
+        let mut check: GitCheck = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        let mut context: Context = HashMap::new();
+        let error = check.check_inner(&mut context).err().unwrap();
+
+        assert!(
+            matches!(error, GitError::FailedSettingHead(_)),
+            "{error:?} should be FailedSettingHead"
+        );
+
+        // Set repository to readonly
+        let mut perms = fs::metadata(&local)?.permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        perms.set_readonly(false);
+        fs::set_permissions(&local, perms)?;
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_hold_updates_outside_of_deploy_windows() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a new commit
+        create_other_repository(&local)?;
+
+        // Only allow deploying on tomorrow, so the window is closed
+        let tomorrow = Utc::now().weekday().succ();
+        let closed_window = DeployWindows::new(
+            vec![format!("{tomorrow} 00:00-24:00").parse()?],
+            Some("UTC"),
+        )?;
+
+        let before_commit_sha = get_last_commit(&local)?;
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        check.set_windows(closed_window);
+        let mut context: Context = HashMap::new();
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(!is_pulled);
+
+        // The pushed file should not be pulled, but it should be in the context
+        assert!(!Path::new(&format!("{local}/2")).exists());
+        assert_eq!(before_commit_sha, get_last_commit(&local)?);
+        let held_commit_sha = context.get("GIT_HELD_COMMIT_SHA").unwrap().clone();
+        assert_ne!(before_commit_sha, held_commit_sha);
+        assert_eq!(
+            &held_commit_sha[0..7],
+            context.get("GIT_HELD_COMMIT_SHORT_SHA").unwrap()
+        );
+
+        // After the window opens, the held commit should be pulled
+        let open_window = DeployWindows::new(vec!["00:00-24:00".parse()?], Some("UTC"))?;
+        check.set_windows(open_window);
+        let mut context: Context = HashMap::new();
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(is_pulled);
+        assert!(Path::new(&format!("{local}/2")).exists());
+        assert_eq!(&held_commit_sha, context.get("GIT_COMMIT_SHA").unwrap());
+        assert_eq!(None, context.get("GIT_HELD_COMMIT_SHA"));
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_only_report_updates_matching_the_paths() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        let other = format!("{local}-other");
+
+        create_empty_repository(&local)?;
+
+        // Create another repository and push a commit outside of the paths
+        create_other_repository(&local)?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        check.set_paths(PathFilter::new(&[String::from("docs")], &[])?);
+        let mut context: Context = HashMap::new();
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(!is_pulled);
+
+        // The file should be pulled anyway, but it shouldn't match
+        assert!(Path::new(&format!("{local}/2")).exists());
+        assert_eq!("2", context.get("GIT_CHANGED_FILES").unwrap());
+        assert_eq!(None, context.get("GIT_MATCHED_FILES"));
+
+        // Push a commit that changes the paths
+        fs::create_dir(format!("{other}/docs"))?;
+        fs::write(format!("{other}/3"), "3")?;
+        create_commit(&other, "docs/index.md", "docs")?;
+        push_all(&other)?;
+
+        let mut context: Context = HashMap::new();
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(is_pulled);
+        assert_eq!(
+            "3\ndocs/index.md",
+            context.get("GIT_CHANGED_FILES").unwrap()
+        );
+        assert_eq!("docs/index.md", context.get("GIT_MATCHED_FILES").unwrap());
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    fn create_commit_info(message: &str) -> repository::GitCommitInformation {
+        repository::GitCommitInformation {
+            commit_sha: Oid::zero(),
+            summary: message.lines().next().unwrap_or_default().to_string(),
+            message: message.to_string(),
+            author_name: String::from("Test"),
+            author_email: String::from("test@example.com"),
+            time: None,
+            trailers: git2::message_trailers_strs(message)
+                .unwrap()
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn it_should_parse_skip_trailers() {
+        assert_eq!(
+            "Deploy: no",
+            "Deploy:no".parse::<SkipTrailer>().unwrap().to_string()
+        );
+        assert_eq!(
+            "Skip-Deploy: true",
+            " Skip-Deploy: true "
+                .parse::<SkipTrailer>()
+                .unwrap()
+                .to_string()
+        );
+        assert!("Deploy".parse::<SkipTrailer>().is_err());
+        assert!("Deploy: ".parse::<SkipTrailer>().is_err());
+        assert!("Do deploy: no".parse::<SkipTrailer>().is_err());
+    }
+
+    #[test]
+    fn it_should_find_skip_directives() {
+        let skip = SkipDirectives::default();
+        let docs = create_commit_info("Update docs [skip deploy]");
+        let trailer = create_commit_info("Update docs\n\nDeploy: NO");
+        let feature = create_commit_info("Add feature\n\nDeploy-Restart: api");
+
+        assert_eq!(
+            Some(String::from("[skip deploy]")),
+            skip.find_skip_reason(slice::from_ref(&docs))
+        );
+        assert_eq!(
+            Some(String::from("Deploy: no")),
+            skip.find_skip_reason(slice::from_ref(&trailer))
+        );
+        assert_eq!(
+            Some(String::from("[gw skip]")),
+            skip.find_skip_reason(&[create_commit_info("[GW SKIP] typo")])
+        );
+        assert_eq!(None, skip.find_skip_reason(slice::from_ref(&feature)));
+
+        // By default only the latest commit matters
+        assert_eq!(
+            None,
+            skip.find_skip_reason(&[feature.clone(), docs.clone()])
+        );
+        assert!(skip
+            .find_skip_reason(&[docs.clone(), feature.clone()])
+            .is_some());
+
+        // With all, every commit has to be marked
+        let skip = SkipDirectives::new(vec![], SkipScope::All);
+        assert!(skip
+            .find_skip_reason(&[docs.clone(), feature.clone()])
+            .is_none());
+        assert!(skip
+            .find_skip_reason(&[docs.clone(), trailer.clone()])
+            .is_some());
+
+        // Custom trailers replace the default
+        let skip = SkipDirectives::new(
+            vec!["Deploy-Restart: api".parse().unwrap()],
+            SkipScope::Latest,
+        );
+        assert!(skip.find_skip_reason(slice::from_ref(&feature)).is_some());
+        assert!(skip.find_skip_reason(slice::from_ref(&trailer)).is_none());
+
+        let skip = SkipDirectives::new(vec![], SkipScope::None);
+        assert!(skip.find_skip_reason(&[docs]).is_none());
+    }
+
+    #[test]
+    fn it_should_pull_but_skip_marked_commits() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        let other = format!("{local}-other");
+
+        create_empty_repository(&local)?;
+        create_other_repository(&local)?;
+
+        // Push a commit that should not be deployed
+        fs::write(format!("{other}/3"), "3")?;
+        cmd!("git", "add", "-A").dir(&other).read()?;
+        cmd!(
+            "git",
+            "commit",
+            "-m",
+            "Update docs\n\nDeploy: no\nDeploy-Restart: api"
+        )
+        .dir(&other)
+        .read()?;
+        push_all(&other)?;
+
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        let mut context: Context = HashMap::new();
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(!is_pulled);
+
+        // The commits should be pulled anyway
+        assert!(Path::new(&format!("{local}/3")).exists());
+        assert_eq!("2", context.get("GIT_COMMIT_COUNT").unwrap());
+        assert_eq!(
+            "Deploy: no\nDeploy-Restart: api",
+            context.get("GIT_COMMIT_TRAILERS").unwrap()
+        );
+
+        let _ = cleanup_repository(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_only_pull_commits_with_valid_signatures() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        let other = format!("{local}-other");
+        let keys = format!("{local}-keys");
+
+        create_empty_repository(&local)?;
+        create_other_repository(&local)?;
+
+        // Create a signing key and allow it
+        fs::create_dir(&keys)?;
+        cmd!(
+            "ssh-keygen",
+            "-q",
+            "-t",
+            "ed25519",
+            "-N",
+            "",
+            "-f",
+            format!("{keys}/id_ed25519")
+        )
+        .read()?;
+        let public_key = fs::read_to_string(format!("{keys}/id_ed25519.pub"))?;
+        fs::write(
+            format!("{keys}/allowed_signers"),
+            format!("test@example.com namespaces=\"git\" {public_key}"),
+        )?;
+        let verifier = SignatureVerifier::new(Some(format!("{keys}/allowed_signers")), vec![])?;
+
+        // The unsigned commit should not be pulled
+        let mut check = GitCheck::open_inner(&local, GitTriggerArgument::Push)?;
+        check.set_verifier(verifier);
+        let mut context: Context = HashMap::new();
+        let result = check.check_inner(&mut context);
+        assert!(
+            matches!(result, Err(GitError::InvalidSignature(_, _))),
+            "{result:?} should be InvalidSignature"
+        );
+        assert!(!Path::new(&format!("{local}/2")).exists());
+
+        // Push a signed commit
+        let signing_key = fs::canonicalize(format!("{keys}/id_ed25519"))?;
+        fs::write(format!("{other}/3"), "3")?;
+        cmd!("git", "add", "-A").dir(&other).read()?;
+        cmd!(
+            "git",
+            "-c",
+            "gpg.format=ssh",
+            "-c",
+            format!("user.signingkey={}", signing_key.to_string_lossy()),
+            "commit",
+            "-S",
+            "-m",
+            "signed"
+        )
+        .dir(&other)
+        .read()?;
+        push_all(&other)?;
+
+        let mut context: Context = HashMap::new();
+        let is_pulled = check.check_inner(&mut context)?;
+        assert!(is_pulled);
+        assert!(Path::new(&format!("{local}/3")).exists());
 
         let _ = cleanup_repository(&local);
+        let _ = fs::remove_dir_all(&keys);
 
         Ok(())
     }
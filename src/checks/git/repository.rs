@@ -2,12 +2,21 @@ use super::{
     credentials::{CredentialAuth, CredentialHandler},
     GitError,
 };
+use crate::paths::PathFilter;
+use chrono::{DateTime, FixedOffset};
 use git2::{
-    AnnotatedCommit, AutotagOption, Config, FetchOptions, Oid, RemoteCallbacks, Repository,
+    build::RepoBuilder, AnnotatedCommit, AutotagOption, Config, ErrorClass, FetchOptions, Oid,
+    RemoteCallbacks, Repository, Signature, Sort, StashFlags, StashSaveOptions, Status,
     StatusOptions,
 };
-use log::{debug, trace};
-use std::{collections::HashMap, slice};
+use log::{debug, info, trace, warn};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::Path,
+    slice,
+    str::FromStr,
+};
 
 pub struct GitRepositoryInformation {
     pub ref_name: String,
@@ -18,12 +27,89 @@ pub struct GitRepositoryInformation {
     pub remote_url: String,
 }
 
+/// The start of the signature block appended to a tag.
+const SIGNATURE_START: &[u8] = b"-----BEGIN ";
+
+/// Information about a pulled commit, for the context.
+#[derive(Debug, Clone)]
+pub struct GitCommitInformation {
+    pub commit_sha: Oid,
+    pub summary: String,
+    pub message: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub time: Option<DateTime<FixedOffset>>,
+    pub trailers: Vec<(String, String)>,
+}
+
 /// A directory that is opened as a git repository.
 ///
 /// It is a wrapper around the underlying `git2` [Repository](git2::Repository).
 pub struct GitRepository {
     repo: Repository,
     auth: Option<CredentialAuth>,
+    target: GitTarget,
+    dirty_policy: DirtyPolicy,
+    dirty_ignores: PathFilter,
+}
+
+/// The remote branch to follow, and how to check it out.
+///
+/// By default it follows the upstream of the checked out branch.
+#[derive(Debug, Clone, Default)]
+pub struct GitTarget {
+    /// The remote to fetch from, by default the upstream remote of the branch (or `origin`).
+    pub remote: Option<String>,
+    /// The branch to follow, by default the checked out branch.
+    pub branch: Option<String>,
+    /// Check out the commits in a detached HEAD, instead of updating the local branch.
+    pub detached: bool,
+}
+
+/// What to do with the local changes in the working tree, before updating.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DirtyPolicy {
+    /// Refuse to update, if there are any local changes, including untracked files.
+    Fail,
+    /// Update if there are only untracked files (e.g. build outputs), but refuse on modified files.
+    #[default]
+    IgnoreUntracked,
+    /// Stash the local changes and reapply them after the update.
+    Stash,
+    /// Throw away the local changes.
+    Discard,
+}
+
+impl FromStr for DirtyPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(DirtyPolicy::Fail),
+            "ignore-untracked" => Ok(DirtyPolicy::IgnoreUntracked),
+            "stash" => Ok(DirtyPolicy::Stash),
+            "discard" => Ok(DirtyPolicy::Discard),
+            s => Err(format!(
+                "cannot parse {s}, valid values: fail, ignore-untracked, stash, discard"
+            )),
+        }
+    }
+}
+
+/// Create the options for fetching, with the authentication callbacks and downloading the tags.
+fn create_fetch_options(auth: Option<CredentialAuth>) -> Result<FetchOptions<'static>, GitError> {
+    // Setup authentication callbacks to fetch the repository
+    let mut cb = RemoteCallbacks::new();
+    let git_config = Config::open_default().map_err(|_| GitError::ConfigLoadingFailed)?;
+    let mut ch = CredentialHandler::new(git_config, auth);
+    cb.credentials(move |url, username, allowed| ch.try_next_credential(url, username, allowed));
+
+    // Set option to download tags automatically
+    let mut opts = FetchOptions::new();
+    opts.remote_callbacks(cb);
+    opts.download_tags(AutotagOption::Auto);
+
+    Ok(opts)
 }
 
 /// Return the 7 characters short hash version for a commit SHA
@@ -32,56 +118,132 @@ pub fn shorthash(sha: &Oid) -> String {
 }
 
 impl GitRepository {
-    /// Open a directory as a GitRepository. Fails if the directory is not a valid git repo.
-    pub fn open(directory: &str) -> Result<Self, GitError> {
+    /// Open a directory as a GitRepository, following the target. Fails if the directory is not a valid git repo.
+    pub fn open(directory: &str, target: GitTarget) -> Result<Self, GitError> {
         let repo = Repository::open(directory).map_err(|err| {
             GitError::NotAGitRepository(String::from(directory), err.message().trim().to_string())
         })?;
 
         // Do a sanity check to fail instantly if there are any issues
-        let git_repo = GitRepository { repo, auth: None };
+        let git_repo = GitRepository {
+            repo,
+            auth: None,
+            target,
+            dirty_policy: DirtyPolicy::default(),
+            dirty_ignores: PathFilter::default(),
+        };
         git_repo.get_repository_information()?;
 
         Ok(git_repo)
     }
 
+    /// Clone the repository from the url to the directory, and open it.
+    ///
+    /// If the target has no branch, the default branch of the remote is checked out.
+    /// If it has no remote, it is called `origin`.
+    pub fn clone(
+        url: &str,
+        directory: &str,
+        target: GitTarget,
+        auth: Option<CredentialAuth>,
+    ) -> Result<Self, GitError> {
+        let mut builder = RepoBuilder::new();
+        builder.fetch_options(create_fetch_options(auth.clone())?);
+        if let Some(remote) = target.remote.clone() {
+            builder.remote_create(move |repo, _, url| repo.remote(&remote, url));
+        }
+        if let Some(branch) = &target.branch {
+            builder.branch(branch);
+        }
+        builder.clone(url, Path::new(directory)).map_err(|err| {
+            GitError::CloneFailed(url.to_string(), err.message().trim().to_string())
+        })?;
+
+        let mut git_repo = GitRepository::open(directory, target)?;
+        git_repo.auth = auth;
+
+        Ok(git_repo)
+    }
+
     pub fn set_auth(&mut self, auth: CredentialAuth) {
         self.auth = Some(auth);
     }
 
+    /// Check if the commits are checked out in a detached HEAD.
+    pub fn is_detached(&self) -> bool {
+        self.target.detached
+    }
+
+    pub fn set_dirty_policy(&mut self, dirty_policy: DirtyPolicy) {
+        self.dirty_policy = dirty_policy;
+    }
+
+    pub fn set_dirty_ignores(&mut self, dirty_ignores: PathFilter) {
+        self.dirty_ignores = dirty_ignores;
+    }
+
     /// Get information about the current repository, for context and usage in GitRepository
+    ///
+    /// By default it follows the upstream of the checked out branch, unless the remote or the branch is set.
     pub fn get_repository_information(&self) -> Result<GitRepositoryInformation, GitError> {
-        let Self { repo, .. } = self;
+        let Self {
+            repo,
+            target:
+                GitTarget {
+                    remote,
+                    branch,
+                    detached,
+                },
+            ..
+        } = self;
         let head = repo.head().map_err(|_| GitError::NotOnABranch)?;
-        let ref_name = head.name().ok_or(GitError::NotOnABranch)?;
-        let commit_sha = head
+        // Without a branch, a detached checkout only follows the tags
+        let (branch_name, ref_name) = match branch {
+            Some(branch) => (branch.clone(), format!("refs/heads/{branch}")),
+            None if *detached => (String::from("HEAD"), String::from("HEAD")),
+            None if head.is_branch() => {
+                let branch_name = head.shorthand().ok_or(GitError::NotOnABranch)?;
+                (branch_name.to_string(), format!("refs/heads/{branch_name}"))
+            }
+            None => return Err(GitError::NotOnABranch),
+        };
+
+        // The deployed commit is HEAD if detached, otherwise the local branch (if it exists already)
+        let local_branch = repo.find_reference(&ref_name).ok().filter(|_| !*detached);
+        let commit_sha = local_branch
+            .as_ref()
+            .unwrap_or(&head)
             .peel_to_commit()
             .map_err(|_| GitError::NotOnABranch)?
             .id();
 
-        let branch_name = head.shorthand().ok_or(GitError::NotOnABranch)?;
-        let remote_buf = repo
-            .branch_upstream_remote(ref_name)
-            .map_err(|_| GitError::NoRemoteForBranch(String::from(branch_name)))?;
-        let remote_name = remote_buf
-            .as_str()
-            .ok_or_else(|| GitError::NoRemoteForBranch(String::from(branch_name)))?;
+        let remote_name = match remote {
+            Some(remote) => remote.clone(),
+            None => match repo.branch_upstream_remote(&ref_name) {
+                Ok(remote_buf) => remote_buf
+                    .as_str()
+                    .ok_or_else(|| GitError::NoRemoteForBranch(branch_name.clone()))?
+                    .to_string(),
+                Err(_) if branch.is_some() || *detached => String::from("origin"),
+                Err(_) => return Err(GitError::NoRemoteForBranch(branch_name)),
+            },
+        };
 
         let remote = repo
-            .find_remote(remote_name)
-            .map_err(|_| GitError::NoRemoteForBranch(String::from(branch_name)))?;
+            .find_remote(&remote_name)
+            .map_err(|_| GitError::NoRemoteForBranch(branch_name.clone()))?;
 
         let remote_url = remote
             .url()
-            .ok_or(GitError::NoRemoteForBranch(String::from(branch_name)))?;
+            .ok_or_else(|| GitError::NoRemoteForBranch(branch_name.clone()))?;
 
         Ok(GitRepositoryInformation {
-            ref_name: ref_name.to_string(),
-            branch_name: branch_name.to_string(),
+            ref_name,
+            branch_name,
             commit_short_sha: shorthash(&commit_sha),
             commit_sha,
             remote_url: remote_url.to_string(),
-            remote_name: remote_name.to_string(),
+            remote_name,
         })
     }
 
@@ -100,20 +262,8 @@ impl GitRepository {
             .find_remote(&remote_name)
             .map_err(|_| GitError::NoRemoteForBranch(branch_name.clone()))?;
 
-        // Setup authentication callbacks to fetch the repository
-        let mut cb = RemoteCallbacks::new();
-        let git_config = Config::open_default().map_err(|_| GitError::ConfigLoadingFailed)?;
-        let mut ch = CredentialHandler::new(git_config, self.auth.clone());
-        cb.credentials(move |url, username, allowed| {
-            ch.try_next_credential(url, username, allowed)
-        });
-
-        // Set option to download tags automatically
-        let mut opts = FetchOptions::new();
-        opts.remote_callbacks(cb);
-        opts.download_tags(AutotagOption::Auto);
-
         // Fetch the remote state
+        let mut opts = create_fetch_options(self.auth.clone())?;
         remote
             .fetch(slice::from_ref(&branch_name), Some(&mut opts), None)
             .map_err(|err| GitError::FetchFailed(err.message().trim().to_string()))?;
@@ -136,24 +286,55 @@ impl GitRepository {
         Ok(fetch_commit)
     }
 
-    pub fn check_if_updatable(&self, fetch_commit: &AnnotatedCommit) -> Result<bool, GitError> {
+    /// Fetch all tags from the remote, independently of the branches.
+    pub fn fetch_tags(&self) -> Result<(), GitError> {
         let Self { repo, .. } = self;
-        let (analysis, _) = repo
-            .merge_analysis(&[fetch_commit])
-            .map_err(|_| GitError::MergeConflict)?;
+        let GitRepositoryInformation {
+            branch_name,
+            remote_name,
+            ..
+        } = self.get_repository_information()?;
 
-        if analysis.is_fast_forward() {
-            trace!("Fetched commit can be fast forwarded.");
-            Ok(true)
-        } else if analysis.is_up_to_date() {
+        trace!("Trying to fetch the tags from {remote_name}.");
+
+        let mut remote = repo
+            .find_remote(&remote_name)
+            .map_err(|_| GitError::NoRemoteForBranch(branch_name))?;
+        let mut opts = create_fetch_options(self.auth.clone())?;
+        opts.download_tags(AutotagOption::All);
+        remote
+            .fetch(&["+refs/tags/*:refs/tags/*"], Some(&mut opts), None)
+            .map_err(|err| GitError::FetchFailed(err.message().trim().to_string()))?;
+
+        Ok(())
+    }
+
+    pub fn check_if_updatable(&self, fetch_commit: &AnnotatedCommit) -> Result<bool, GitError> {
+        let Self { repo, target, .. } = self;
+        let GitRepositoryInformation {
+            ref_name,
+            commit_sha,
+            ..
+        } = self.get_repository_information()?;
+
+        if !target.detached && repo.find_reference(&ref_name).is_err() {
+            trace!("Branch {ref_name} doesn't exist yet, it will be created.");
+            return Ok(true);
+        }
+
+        let fetch_commit_id = fetch_commit.id();
+        let is_descendant = |commit, ancestor| {
+            repo.graph_descendant_of(commit, ancestor)
+                .map_err(|_| GitError::MergeConflict)
+        };
+        if fetch_commit_id == commit_sha || is_descendant(commit_sha, fetch_commit_id)? {
             trace!("Fetched commit is up to date.");
             Ok(false)
+        } else if is_descendant(fetch_commit_id, commit_sha)? {
+            trace!("Fetched commit can be fast forwarded.");
+            Ok(true)
         } else {
-            if analysis.is_unborn() {
-                debug!("Fetched commit is not pointing to a valid branch (unborn), failing.");
-            } else if analysis.is_normal() {
-                debug!("Fetched commit is a merge conflict, failing.");
-            }
+            debug!("Fetched commit is a merge conflict, failing.");
             Err(GitError::MergeConflict)
         }
     }
@@ -175,6 +356,9 @@ impl GitRepository {
         revwalk.push(last_commit_id).map_err(|_| {
             GitError::FetchFailed("fetched commit is not on this branch".to_string())
         })?;
+        revwalk
+            .hide(first_commit_id)
+            .map_err(|_| GitError::TagMatchingFailed)?;
         trace!(
             "Walking through fetched commits between {}..{}.",
             shorthash(&last_commit_id),
@@ -221,8 +405,441 @@ impl GitRepository {
         Ok(tags)
     }
 
-    pub fn pull(&self, commit_id: Oid) -> Result<(), GitError> {
+    /// Find all the tags matching the pattern, with the commit and its time, on any branch.
+    pub fn find_all_tags(&self, pattern: &str) -> Result<Vec<(String, Oid, i64)>, GitError> {
+        let Self { repo, .. } = self;
+        let tag_names = repo
+            .tag_names(Some(pattern))
+            .map_err(|_| GitError::TagMatchingFailed)?;
+
+        // If a tag does not point to a valid commit, ignore it
+        Ok(tag_names
+            .iter()
+            .flatten()
+            .flat_map(|tag_name| {
+                repo.find_reference(&format!("refs/tags/{tag_name}"))
+                    .and_then(|tag| tag.peel_to_commit())
+                    .map(|commit| (tag_name.to_string(), commit.id(), commit.time().seconds()))
+            })
+            .collect())
+    }
+
+    /// Find the tags pointing to the commit or one of its ancestors.
+    pub fn find_reachable_tags(&self, commit_id: Oid) -> Result<Vec<String>, GitError> {
+        let Self { repo, .. } = self;
+        let tag_names = repo
+            .tag_names(None)
+            .map_err(|_| GitError::TagMatchingFailed)?;
+
+        Ok(tag_names
+            .iter()
+            .flatten()
+            .filter(|tag_name| {
+                repo.find_reference(&format!("refs/tags/{tag_name}"))
+                    .and_then(|tag| tag.peel_to_commit())
+                    .is_ok_and(|tag| {
+                        tag.id() == commit_id
+                            || repo
+                                .graph_descendant_of(commit_id, tag.id())
+                                .unwrap_or(false)
+                    })
+            })
+            .map(String::from)
+            .collect())
+    }
+
+    /// List the files that changed between the two commits, including the deleted and renamed ones.
+    pub fn find_changed_files(&self, from: Oid, to: Oid) -> Result<Vec<String>, GitError> {
+        let Self { repo, .. } = self;
+        let find_tree = |oid: Oid| {
+            repo.find_commit(oid)
+                .and_then(|commit| commit.tree())
+                .map_err(|err| GitError::DiffFailed(err.message().trim().to_string()))
+        };
+        let from_tree = find_tree(from)?;
+        let to_tree = find_tree(to)?;
+
+        let diff = repo
+            .diff_tree_to_tree(Some(&from_tree), Some(&to_tree), None)
+            .map_err(|err| GitError::DiffFailed(err.message().trim().to_string()))?;
+
+        let files: BTreeSet<String> = diff
+            .deltas()
+            .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+            .flatten()
+            .map(|path| path.to_string_lossy().replace('\\', "/"))
+            .collect();
+        trace!(
+            "There are {} changed files between {} and {}.",
+            files.len(),
+            shorthash(&from),
+            shorthash(&to)
+        );
+
+        Ok(files.into_iter().collect())
+    }
+
+    /// List the commits after the first commit until the second one, starting with the newest.
+    pub fn find_commits(&self, from: Oid, to: Oid) -> Result<Vec<GitCommitInformation>, GitError> {
         let Self { repo, .. } = self;
+        let map_err = |err: git2::Error| GitError::LogFailed(err.message().trim().to_string());
+
+        let mut revwalk = repo.revwalk().map_err(map_err)?;
+        revwalk
+            .set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+            .map_err(map_err)?;
+        revwalk.push(to).map_err(map_err)?;
+        revwalk.hide(from).map_err(map_err)?;
+
+        let commits = revwalk
+            .map(|oid| {
+                let commit = repo.find_commit(oid.map_err(map_err)?).map_err(map_err)?;
+                let author = commit.author();
+                let time = commit.time();
+                let message = commit.message().unwrap_or_default();
+                let trailers = git2::message_trailers_strs(message)
+                    .map(|trailers| {
+                        trailers
+                            .iter()
+                            .map(|(key, value)| (key.to_string(), value.to_string()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                Ok(GitCommitInformation {
+                    commit_sha: commit.id(),
+                    summary: commit.summary().unwrap_or_default().to_string(),
+                    message: message.trim_end().to_string(),
+                    author_name: author.name().unwrap_or_default().to_string(),
+                    author_email: author.email().unwrap_or_default().to_string(),
+                    time: FixedOffset::east_opt(time.offset_minutes() * 60).and_then(|offset| {
+                        DateTime::from_timestamp(time.seconds(), 0)
+                            .map(|time| time.with_timezone(&offset))
+                    }),
+                    trailers,
+                })
+            })
+            .collect::<Result<Vec<_>, GitError>>()?;
+        trace!(
+            "There are {} commits between {} and {}.",
+            commits.len(),
+            shorthash(&from),
+            shorthash(&to)
+        );
+
+        Ok(commits)
+    }
+
+    /// Get the signature and the signed data of the commit, if it is signed.
+    pub fn extract_commit_signature(&self, commit: Oid) -> Option<(Vec<u8>, Vec<u8>)> {
+        let Self { repo, .. } = self;
+        let (signature, signed_data) = repo.extract_signature(&commit, None).ok()?;
+
+        Some((signature.to_vec(), signed_data.to_vec()))
+    }
+
+    /// Get the signature and the signed data of an annotated tag, if it is signed.
+    ///
+    /// The signature of a tag is appended to the end of the tag object.
+    pub fn extract_tag_signature(&self, tag_name: &str) -> Option<(Vec<u8>, Vec<u8>)> {
+        let Self { repo, .. } = self;
+        let tag = repo
+            .find_reference(&format!("refs/tags/{tag_name}"))
+            .ok()?
+            .peel_to_tag()
+            .ok()?;
+        let odb = repo.odb().ok()?;
+        let object = odb.read(tag.id()).ok()?;
+        let data = object.data();
+
+        let start = data
+            .windows(SIGNATURE_START.len())
+            .rposition(|window| window == SIGNATURE_START)?;
+        let (signed_data, signature) = data.split_at(start);
+
+        Some((signature.to_vec(), signed_data.to_vec()))
+    }
+
+    /// Find the local changes that block the update, based on the dirty policy and the ignores.
+    fn find_blocking_changes(
+        &self,
+        ignores_untracked: bool,
+    ) -> Result<Vec<(String, bool)>, GitError> {
+        let Self {
+            dirty_policy,
+            dirty_ignores,
+            ..
+        } = self;
+
+        Ok(self
+            .find_local_changes()?
+            .into_iter()
+            .filter(|(path, _)| dirty_ignores.is_empty() || !dirty_ignores.matches(path))
+            .filter(|(_, untracked)| {
+                !(*untracked
+                    && (ignores_untracked || *dirty_policy == DirtyPolicy::IgnoreUntracked))
+            })
+            .collect())
+    }
+
+    /// Find the local changes in the paths ignored by the dirty ignores.
+    fn find_ignored_changes(&self) -> Result<Vec<String>, GitError> {
+        let Self { dirty_ignores, .. } = self;
+        if dirty_ignores.is_empty() {
+            return Ok(vec![]);
+        }
+
+        Ok(self
+            .find_local_changes()?
+            .into_iter()
+            .map(|(path, _)| path)
+            .filter(|path| dirty_ignores.matches(path))
+            .collect())
+    }
+
+    /// Find the changed and untracked files, and whether they are untracked.
+    fn find_local_changes(&self) -> Result<Vec<(String, bool)>, GitError> {
+        let Self { repo, .. } = self;
+        let statuses = repo
+            .statuses(Some(
+                StatusOptions::new()
+                    .include_ignored(false)
+                    .include_untracked(true)
+                    .recurse_untracked_dirs(true),
+            ))
+            .map_err(|_| GitError::DirtyWorkingTree)?;
+
+        Ok(statuses
+            .iter()
+            .filter_map(|entry| {
+                let path = entry.path()?.to_string();
+                let untracked = entry.status() == Status::WT_NEW;
+                Some((path, untracked))
+            })
+            .collect())
+    }
+
+    /// Check if there are any local changes, including the ones that don't block the update.
+    fn has_local_changes(&self) -> Result<bool, GitError> {
+        let Self { repo, .. } = self;
+        let statuses = repo
+            .statuses(Some(StatusOptions::new().include_ignored(false)))
+            .map_err(|_| GitError::DirtyWorkingTree)?;
+
+        Ok(!statuses.is_empty())
+    }
+
+    /// Make sure that the local changes don't block the update, based on the dirty policy.
+    ///
+    /// Returns true if the changes are stashed, and they have to be reapplied after the update.
+    fn prepare_working_tree(&mut self, ignores_untracked: bool) -> Result<bool, GitError> {
+        let blocking_changes = self.find_blocking_changes(ignores_untracked)?;
+        if blocking_changes.is_empty() {
+            return Ok(false);
+        }
+        let ignored_changes = if self.dirty_policy == DirtyPolicy::Stash {
+            self.find_ignored_changes()?
+        } else {
+            vec![]
+        };
+
+        let Self {
+            repo, dirty_policy, ..
+        } = self;
+        let paths = blocking_changes
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match dirty_policy {
+            DirtyPolicy::Fail | DirtyPolicy::IgnoreUntracked => {
+                warn!("The update is blocked by local changes in {paths}.");
+                Err(GitError::DirtyWorkingTree)
+            }
+            DirtyPolicy::Stash => {
+                // The stash would take the ignored changes too, and partial stashes with
+                // untracked files cannot be reapplied, so don't update without them
+                if !ignored_changes.is_empty() {
+                    let ignored_paths = ignored_changes.join(", ");
+                    warn!(
+                        "Stashing {paths} would also stash the ignored changes in {ignored_paths}."
+                    );
+                    return Err(GitError::StashFailed(format!(
+                        "the ignored local changes in {ignored_paths} would be stashed too"
+                    )));
+                }
+
+                info!("Stashing the local changes in {paths}.");
+                let signature = repo
+                    .signature()
+                    .or_else(|_| Signature::now("gw", "gw@localhost"))
+                    .map_err(|err| GitError::StashFailed(err.message().trim().to_string()))?;
+                let mut options = StashSaveOptions::new(signature);
+                // Stash every local change, applying a partial stash with untracked files fails
+                options.flags(Some(StashFlags::INCLUDE_UNTRACKED));
+                repo.stash_save_ext(Some(&mut options))
+                    .map_err(|err| GitError::StashFailed(err.message().trim().to_string()))?;
+
+                Ok(true)
+            }
+            DirtyPolicy::Discard => {
+                warn!("Discarding the local changes in {paths}.");
+                let mut checkout = git2::build::CheckoutBuilder::default();
+                checkout.force();
+                let mut has_tracked_changes = false;
+                for (path, untracked) in &blocking_changes {
+                    if *untracked {
+                        if let Some(workdir) = repo.workdir() {
+                            let _ = fs::remove_file(workdir.join(path));
+                        }
+                    } else {
+                        checkout.path(path.as_str());
+                        has_tracked_changes = true;
+                    }
+                }
+                if has_tracked_changes {
+                    repo.checkout_head(Some(&mut checkout))
+                        .map_err(|_| GitError::DirtyWorkingTree)?;
+                }
+
+                Ok(false)
+            }
+        }
+    }
+
+    /// Reapply the stashed local changes. If they conflict with the update, they are kept in the stash.
+    fn restore_working_tree(&mut self, stashed: bool) {
+        if !stashed {
+            return;
+        }
+
+        match self.repo.stash_pop(0, None) {
+            Ok(()) => info!("Reapplied the stashed local changes."),
+            Err(err) => warn!(
+                "The local changes cannot be reapplied ({}), they are kept in the stash.",
+                err.message().trim()
+            ),
+        }
+    }
+
+    /// Check out the commit and point the branch to it.
+    ///
+    /// The untracked files can be ignored regardless of the dirty policy (e.g. the build outputs on rollback).
+    fn update_to(
+        &mut self,
+        commit_id: Oid,
+        message: &str,
+        ignores_untracked: bool,
+    ) -> Result<(), GitError> {
+        let GitRepositoryInformation { ref_name, .. } = self.get_repository_information()?;
+        let fetch_short = shorthash(&commit_id);
+
+        let stashed = self.prepare_working_tree(ignores_untracked)?;
+
+        // If there are local changes left, only update the files that aren't changed
+        let mut checkout = git2::build::CheckoutBuilder::default();
+        if self.has_local_changes()? {
+            checkout.safe();
+        } else {
+            checkout.force();
+        }
+
+        let Self { repo, target, .. } = self;
+        let result = repo
+            .find_object(commit_id, None)
+            .and_then(|commit| repo.checkout_tree(&commit, Some(&mut checkout)))
+            .map_err(|err| {
+                if err.class() == ErrorClass::Checkout {
+                    warn!(
+                        "The update conflicts with local changes: {}",
+                        err.message().trim()
+                    );
+                    GitError::DirtyWorkingTree
+                } else {
+                    GitError::FailedSettingHead(fetch_short.clone())
+                }
+            })
+            .and_then(|_| {
+                if target.detached {
+                    return repo
+                        .set_head_detached(commit_id)
+                        .map_err(|_| GitError::FailedSettingHead(fetch_short.clone()));
+                }
+                repo.reference(&ref_name, commit_id, true, message)
+                    .map_err(|_| GitError::FailedSettingHead(fetch_short.clone()))?;
+                repo.set_head(&ref_name)
+                    .map_err(|_| GitError::FailedSettingHead(fetch_short.clone()))
+            });
+
+        self.restore_working_tree(stashed);
+
+        result
+    }
+
+    /// Write the files of the commit to another directory, without changing the working tree.
+    pub fn export(&self, commit_id: Oid, directory: &Path) -> Result<(), GitError> {
+        let mut checkout = git2::build::CheckoutBuilder::default();
+        checkout.force().update_index(false).target_dir(directory);
+
+        self.repo
+            .find_commit(commit_id)
+            .and_then(|commit| commit.tree())
+            .and_then(|tree| {
+                self.repo
+                    .checkout_tree(tree.as_object(), Some(&mut checkout))
+            })
+            .map_err(|err| {
+                GitError::ExportFailed(shorthash(&commit_id), err.message().trim().to_string())
+            })?;
+
+        debug!(
+            "Checked out {} to {}.",
+            shorthash(&commit_id),
+            directory.display()
+        );
+
+        Ok(())
+    }
+
+    /// Reset the branch and the working tree to the commit, even if it is not a fast-forward.
+    pub fn reset(&mut self, commit_id: Oid) -> Result<(), GitError> {
+        let GitRepositoryInformation { branch_name, .. } = self.get_repository_information()?;
+
+        trace!("Resetting {branch_name}.");
+
+        let fetch_short = shorthash(&commit_id);
+        self.update_to(
+            commit_id,
+            &format!("Reset: Setting {branch_name} to id: {commit_id}."),
+            false,
+        )?;
+
+        debug!("Reset to {} on branch {}.", fetch_short, branch_name);
+
+        Ok(())
+    }
+
+    /// Reset the branch and the working tree to an earlier commit after a failed update.
+    ///
+    /// The untracked files (e.g. the outputs of the failed build) never block rolling back.
+    pub fn roll_back(&mut self, commit_id: Oid) -> Result<(), GitError> {
+        let GitRepositoryInformation { branch_name, .. } = self.get_repository_information()?;
+
+        trace!("Rolling back {branch_name}.");
+
+        let fetch_short = shorthash(&commit_id);
+        self.update_to(
+            commit_id,
+            &format!("Rollback: Setting {branch_name} to id: {commit_id}."),
+            true,
+        )?;
+
+        debug!("Rolled back to {} on branch {}.", fetch_short, branch_name);
+
+        Ok(())
+    }
+
+    pub fn pull(&mut self, commit_id: Oid) -> Result<(), GitError> {
         let GitRepositoryInformation {
             branch_name,
             ref_name,
@@ -231,29 +848,12 @@ impl GitRepository {
 
         trace!("Pulling {branch_name}.");
 
-        if !repo
-            .statuses(Some(StatusOptions::new().include_ignored(false)))
-            .map_err(|_| GitError::DirtyWorkingTree)?
-            .is_empty()
-        {
-            return Err(GitError::DirtyWorkingTree);
-        }
-
         let msg = format!("Fast-Forward: Setting {} to id: {}.", ref_name, commit_id);
 
         let fetch_short = shorthash(&commit_id);
         trace!("Setting {} to id: {}.", ref_name, fetch_short);
 
-        let mut branch_ref = repo
-            .find_reference(&ref_name)
-            .map_err(|_| GitError::NotOnABranch)?;
-        branch_ref
-            .set_target(commit_id, &msg)
-            .map_err(|_| GitError::FailedSettingHead(fetch_short.to_string()))?;
-        repo.set_head(&ref_name)
-            .map_err(|_| GitError::FailedSettingHead(fetch_short.to_string()))?;
-        repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))
-            .map_err(|_| GitError::FailedSettingHead(fetch_short.to_string()))?;
+        self.update_to(commit_id, &msg, false)?;
 
         debug!("Checked out {} on branch {}.", fetch_short, branch_name);
 
@@ -0,0 +1,302 @@
+use super::{
+    repository::{shorthash, GitRepository},
+    GitError,
+};
+use crate::context::TempDirectory;
+use duct::cmd;
+use git2::Oid;
+use log::{debug, info};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const SSH_SIGNATURE: &[u8] = b"-----BEGIN SSH SIGNATURE-----";
+const PGP_SIGNATURE: &[u8] = b"-----BEGIN PGP SIGNATURE-----";
+/// The namespace that git uses for SSH signatures.
+const SSH_NAMESPACE: &str = "git";
+
+/// Verify the signatures of the commits and tags before pulling them.
+///
+/// SSH signatures are checked with `ssh-keygen` against an allowed signers file,
+/// GPG signatures with `gpg` against the keyring, and the key has to be in the allowed fingerprints.
+#[derive(Debug, Clone)]
+pub struct SignatureVerifier {
+    allowed_signers: Option<PathBuf>,
+    gpg_keys: Vec<String>,
+}
+
+impl SignatureVerifier {
+    /// Create a verifier with an allowed signers file for SSH, and the fingerprints of the GPG keys.
+    pub fn new(allowed_signers: Option<String>, gpg_keys: Vec<String>) -> Result<Self, GitError> {
+        let allowed_signers = allowed_signers
+            .map(|path| fs::canonicalize(&path).map_err(|_| GitError::AllowedSignersNotFound(path)))
+            .transpose()?;
+        let gpg_keys = gpg_keys
+            .into_iter()
+            .map(Self::parse_gpg_key)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            allowed_signers,
+            gpg_keys,
+        })
+    }
+
+    /// Accept only full fingerprints or long key IDs, because short suffixes can collide.
+    fn parse_gpg_key(key: String) -> Result<String, GitError> {
+        let normalized = key.replace(' ', "").to_uppercase();
+        let normalized = normalized.strip_prefix("0X").unwrap_or(&normalized);
+        if matches!(normalized.len(), 16 | 40 | 64)
+            && normalized.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            Ok(normalized.to_string())
+        } else {
+            Err(GitError::InvalidGpgKey(key))
+        }
+    }
+
+    /// Verify the signature of the tag (if it is signed) or the commit.
+    pub(super) fn verify(
+        &self,
+        repo: &GitRepository,
+        commit: Oid,
+        tag_name: Option<&str>,
+    ) -> Result<(), GitError> {
+        let (object, (signature, signed_data)) = match tag_name
+            .and_then(|tag| Some((tag, repo.extract_tag_signature(tag)?)))
+        {
+            Some((tag, signature)) => (format!("tag {tag}"), signature),
+            None => (
+                format!("commit {}", shorthash(&commit)),
+                repo.extract_commit_signature(commit).ok_or_else(|| {
+                    GitError::InvalidSignature(shorthash(&commit), String::from("it is not signed"))
+                })?,
+            ),
+        };
+        let signer = self
+            .verify_signature(&commit.to_string(), &signature, &signed_data)
+            .map_err(|reason| GitError::InvalidSignature(shorthash(&commit), reason))?;
+        info!("Verified the signature of {object} by {signer}.");
+
+        Ok(())
+    }
+
+    /// Verify the signature of the data, and return the signer.
+    fn verify_signature(
+        &self,
+        id: &str,
+        signature: &[u8],
+        signed_data: &[u8],
+    ) -> Result<String, String> {
+        // Write the signature to a private directory for the external commands,
+        // the signed data is passed on the standard input
+        let mut directory = TempDirectory::new();
+        let signature_path = directory
+            .write(&format!("{id}.sig"), signature)
+            .map_err(|err| format!("cannot write the signature ({err})"))?;
+
+        self.verify_files(signature, &signature_path, signed_data)
+    }
+
+    fn verify_files(
+        &self,
+        signature: &[u8],
+        signature_path: &Path,
+        signed_data: &[u8],
+    ) -> Result<String, String> {
+        if signature.starts_with(SSH_SIGNATURE) {
+            match &self.allowed_signers {
+                Some(allowed_signers) => {
+                    Self::verify_ssh(allowed_signers, signature_path, signed_data)
+                }
+                None => Err(String::from("SSH signatures are not allowed")),
+            }
+        } else if signature.starts_with(PGP_SIGNATURE) {
+            if self.gpg_keys.is_empty() {
+                Err(String::from("GPG signatures are not allowed"))
+            } else {
+                self.verify_gpg(signature_path, signed_data)
+            }
+        } else {
+            Err(String::from("the signature format is unknown"))
+        }
+    }
+
+    fn verify_ssh(
+        allowed_signers: &Path,
+        signature_path: &Path,
+        signed_data: &[u8],
+    ) -> Result<String, String> {
+        let output = cmd!(
+            "ssh-keygen",
+            "-Y",
+            "find-principals",
+            "-f",
+            allowed_signers,
+            "-s",
+            signature_path
+        )
+        .stderr_to_stdout()
+        .stdout_capture()
+        .unchecked()
+        .run()
+        .map_err(|err| format!("cannot run ssh-keygen ({err})"))?;
+        let principals = String::from_utf8_lossy(&output.stdout).to_string();
+        if !output.status.success() {
+            return Err(String::from("the key is not in the allowed signers"));
+        }
+
+        for principal in principals.lines().map(str::trim).filter(|p| !p.is_empty()) {
+            let output = cmd!(
+                "ssh-keygen",
+                "-Y",
+                "verify",
+                "-f",
+                allowed_signers,
+                "-I",
+                principal,
+                "-n",
+                SSH_NAMESPACE,
+                "-s",
+                signature_path
+            )
+            .stdin_bytes(signed_data)
+            .stderr_to_stdout()
+            .stdout_capture()
+            .unchecked()
+            .run()
+            .map_err(|err| format!("cannot run ssh-keygen ({err})"))?;
+            if output.status.success() {
+                return Ok(principal.to_string());
+            }
+            debug!(
+                "Signature is not valid for {principal}: {}",
+                String::from_utf8_lossy(&output.stdout).trim()
+            );
+        }
+
+        Err(String::from("the signature is not valid"))
+    }
+
+    fn verify_gpg(&self, signature_path: &Path, signed_data: &[u8]) -> Result<String, String> {
+        let output = cmd!(
+            "gpg",
+            "--batch",
+            "--status-fd",
+            "1",
+            "--verify",
+            signature_path,
+            "-"
+        )
+        .stdin_bytes(signed_data)
+        .stderr_null()
+        .stdout_capture()
+        .unchecked()
+        .run()
+        .map_err(|err| format!("cannot run gpg ({err})"))?;
+
+        self.check_gpg_status(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Check the status output of gpg, and return the allowed fingerprint that signed it.
+    fn check_gpg_status(&self, status: &str) -> Result<String, String> {
+        // A valid signature can still be made by a revoked or expired key
+        for line in status.lines() {
+            if line.starts_with("[GNUPG:] REVKEYSIG ") {
+                return Err(String::from("the key is revoked"));
+            }
+            if line.starts_with("[GNUPG:] EXPKEYSIG ") {
+                return Err(String::from("the key is expired"));
+            }
+        }
+
+        // The status line is "[GNUPG:] VALIDSIG <fingerprint> ... <primary key fingerprint>"
+        let fingerprints: Vec<String> = status
+            .lines()
+            .find_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+            .map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                [fields.first(), fields.last()]
+                    .into_iter()
+                    .flatten()
+                    .map(|fingerprint| fingerprint.to_uppercase())
+                    .collect()
+            })
+            .ok_or_else(|| String::from("the signature is not valid"))?;
+
+        fingerprints
+            .iter()
+            .find(|fingerprint| {
+                self.gpg_keys
+                    .iter()
+                    .any(|key| Self::matches_gpg_key(fingerprint, key))
+            })
+            .cloned()
+            .ok_or_else(|| {
+                let fingerprint = fingerprints.first().map_or("-", String::as_str);
+                format!("the key {fingerprint} is not allowed")
+            })
+    }
+
+    /// The long key ID is the last 64 bits of a v4 fingerprint, and the first 64 bits of a v5 one.
+    fn matches_gpg_key(fingerprint: &str, key: &str) -> bool {
+        match (key.len(), fingerprint.len()) {
+            (16, 64) => fingerprint.starts_with(key),
+            (16, _) => fingerprint.ends_with(key),
+            _ => fingerprint == key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    const FINGERPRINT: &str = "5A4E8B2C9F1D3E7A6B0C4D2E7910E98532E803CB";
+    const VALIDSIG: &str = "[GNUPG:] VALIDSIG 5A4E8B2C9F1D3E7A6B0C4D2E7910E98532E803CB 2026-10-15 1760000000 0 4 0 22 10 00 5A4E8B2C9F1D3E7A6B0C4D2E7910E98532E803CB";
+
+    fn create_verifier(key: &str) -> Result<SignatureVerifier, GitError> {
+        SignatureVerifier::new(None, vec![key.to_string()])
+    }
+
+    #[test]
+    fn it_should_only_accept_fingerprints_and_long_key_ids() {
+        assert!(create_verifier(FINGERPRINT).is_ok());
+        assert!(create_verifier("7910 E985 32E8 03CB").is_ok());
+        assert!(create_verifier("0x7910e98532e803cb").is_ok());
+
+        assert!(create_verifier("").is_err());
+        assert!(create_verifier("32E803CB").is_err());
+        assert!(create_verifier("not a fingerprint").is_err());
+    }
+
+    #[test]
+    fn it_should_check_the_gpg_status() -> Result<(), Box<dyn Error>> {
+        let verifier = create_verifier("7910e98532e803cb")?;
+        assert_eq!(
+            Ok(String::from(FINGERPRINT)),
+            verifier.check_gpg_status(VALIDSIG)
+        );
+        assert!(verifier
+            .check_gpg_status("[GNUPG:] BADSIG 7910E98532E803CB")
+            .is_err());
+
+        // Other keys, even if they share a short suffix, should not be allowed
+        let verifier = create_verifier("1111111132E803CB")?;
+        assert_eq!(
+            Err(format!("the key {FINGERPRINT} is not allowed")),
+            verifier.check_gpg_status(VALIDSIG)
+        );
+
+        // Revoked and expired keys should not be allowed
+        let verifier = create_verifier(FINGERPRINT)?;
+        let revoked = format!("[GNUPG:] REVKEYSIG 7910E98532E803CB test\n{VALIDSIG}");
+        assert!(verifier.check_gpg_status(&revoked).is_err());
+        let expired = format!("[GNUPG:] EXPKEYSIG 7910E98532E803CB test\n{VALIDSIG}");
+        assert!(verifier.check_gpg_status(&expired).is_err());
+
+        Ok(())
+    }
+}
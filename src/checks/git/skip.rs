@@ -0,0 +1,134 @@
+use super::repository::GitCommitInformation;
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+/// The markers in the commit message, that skip the deploy.
+const SKIP_MARKERS: [&str; 2] = ["[skip deploy]", "[gw skip]"];
+/// The trailer that skips the deploy, if no other is given.
+const SKIP_TRAILER: (&str, &str) = ("Deploy", "no");
+
+/// A commit trailer that skips the deploy (e.g. "Deploy: no").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkipTrailer {
+    key: String,
+    value: String,
+}
+
+impl FromStr for SkipTrailer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once(':')
+            .ok_or_else(|| format!("cannot parse trailer {s}, use e.g. \"Deploy: no\""))?;
+        let (key, value) = (key.trim(), value.trim());
+
+        if key.is_empty() || key.contains(char::is_whitespace) || value.is_empty() {
+            return Err(format!("cannot parse trailer {s}, use e.g. \"Deploy: no\""));
+        }
+
+        Ok(SkipTrailer {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+impl Display for SkipTrailer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.value)
+    }
+}
+
+impl SkipTrailer {
+    fn matches(&self, key: &str, value: &str) -> bool {
+        self.key.eq_ignore_ascii_case(key.trim()) && self.value.eq_ignore_ascii_case(value.trim())
+    }
+}
+
+/// Which of the pulled commits have to be marked to skip the deploy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SkipScope {
+    /// Skip if the newest pulled commit is marked.
+    #[default]
+    Latest,
+    /// Skip only if every pulled commit is marked.
+    All,
+    /// Never skip the deploy.
+    None,
+}
+
+impl FromStr for SkipScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(SkipScope::Latest),
+            "all" => Ok(SkipScope::All),
+            "none" => Ok(SkipScope::None),
+            s => Err(format!("cannot parse {s}, valid values: latest, all, none")),
+        }
+    }
+}
+
+/// The directives in the commit messages, that pull the commits without deploying.
+///
+/// A commit is marked if its message contains `[skip deploy]` or `[gw skip]`,
+/// or it has one of the trailers (by default `Deploy: no`).
+#[derive(Debug, Clone)]
+pub struct SkipDirectives {
+    trailers: Vec<SkipTrailer>,
+    scope: SkipScope,
+}
+
+impl Default for SkipDirectives {
+    fn default() -> Self {
+        Self::new(vec![], SkipScope::default())
+    }
+}
+
+impl SkipDirectives {
+    /// Create the directives with the trailers, by default `Deploy: no`.
+    pub fn new(trailers: Vec<SkipTrailer>, scope: SkipScope) -> Self {
+        let trailers = if trailers.is_empty() {
+            vec![SkipTrailer {
+                key: SKIP_TRAILER.0.to_string(),
+                value: SKIP_TRAILER.1.to_string(),
+            }]
+        } else {
+            trailers
+        };
+
+        Self { trailers, scope }
+    }
+
+    /// Find the reason to skip the commit, if it is marked.
+    fn find_reason(&self, commit: &GitCommitInformation) -> Option<String> {
+        let message = commit.message.to_lowercase();
+        if let Some(marker) = SKIP_MARKERS.iter().find(|m| message.contains(*m)) {
+            return Some(marker.to_string());
+        }
+
+        self.trailers
+            .iter()
+            .find(|trailer| commit.trailers.iter().any(|(k, v)| trailer.matches(k, v)))
+            .map(|trailer| trailer.to_string())
+    }
+
+    /// Check if the pulled commits (starting with the newest) should skip the deploy.
+    ///
+    /// Returns the reason for skipping, or `None` if the commits should be deployed.
+    pub fn find_skip_reason(&self, commits: &[GitCommitInformation]) -> Option<String> {
+        match self.scope {
+            SkipScope::Latest => commits.first().and_then(|c| self.find_reason(c)),
+            SkipScope::All => commits
+                .iter()
+                .map(|c| self.find_reason(c))
+                .collect::<Option<Vec<_>>>()
+                .and_then(|reasons| reasons.into_iter().next()),
+            SkipScope::None => None,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use semver::{Prerelease, Version, VersionReq};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+/// Parse a tag as a semantic version, with an optional `v` prefix (e.g. "v2.1.0").
+pub fn parse_version(tag_name: &str) -> Option<Version> {
+    let version = tag_name.strip_prefix(['v', 'V']).unwrap_or(tag_name);
+
+    Version::parse(version).ok()
+}
+
+/// A range of semantic versions to deploy (e.g. "^2.1" or ">=1.4, <2").
+///
+/// Pre-releases are skipped, unless they are allowed.
+#[derive(Debug, Clone)]
+pub struct VersionRange {
+    requirement: VersionReq,
+    prerelease: bool,
+}
+
+impl FromStr for VersionRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let requirement = VersionReq::parse(s)
+            .map_err(|err| format!("cannot parse version range {s} ({err})"))?;
+
+        Ok(VersionRange {
+            requirement,
+            prerelease: false,
+        })
+    }
+}
+
+impl Display for VersionRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.requirement)
+    }
+}
+
+impl VersionRange {
+    /// Also match the pre-releases (e.g. "2.2.0-rc.1") of the versions in the range.
+    pub fn set_prerelease(&mut self, prerelease: bool) {
+        self.prerelease = prerelease;
+    }
+
+    /// Check if the version is in the range.
+    pub fn matches(&self, version: &Version) -> bool {
+        if version.pre.is_empty() {
+            self.requirement.matches(version)
+        } else if self.prerelease {
+            let release = Version {
+                pre: Prerelease::EMPTY,
+                ..version.clone()
+            };
+            self.requirement.matches(version) || self.requirement.matches(&release)
+        } else {
+            false
+        }
+    }
+
+    /// Select the highest version from the tags in the range, that is higher than the current one.
+    pub fn select<T>(
+        &self,
+        tags: impl IntoIterator<Item = (String, T)>,
+        current: Option<&Version>,
+    ) -> Option<(String, Version, T)> {
+        tags.into_iter()
+            .filter_map(|(tag_name, value)| {
+                let version = parse_version(&tag_name)?;
+                Some((tag_name, version, value))
+            })
+            .filter(|(_, version, _)| self.matches(version))
+            .filter(|(_, version, _)| current.is_none_or(|current| version > current))
+            .max_by(|(_, a, _), (_, b, _)| a.cmp(b))
+    }
+}
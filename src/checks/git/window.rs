@@ -0,0 +1,243 @@
+use super::GitError;
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+const MINUTES_IN_DAY: u32 = 24 * 60;
+
+/// A recurring window on some days of the week, when updates are allowed (e.g. "Mon-Thu 09:00-16:00").
+///
+/// If the end is before the start, the window goes over midnight to the next day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeployWindow {
+    source: String,
+    days: [bool; 7],
+    start: u32,
+    end: u32,
+}
+
+fn parse_weekday(day: &str) -> Result<Weekday, String> {
+    Weekday::from_str(day).map_err(|_| format!("cannot parse day {day}, use e.g. Mon or Friday"))
+}
+
+fn parse_time(time: &str) -> Result<u32, String> {
+    let error = || format!("cannot parse time {time}, use HH:MM, e.g. 09:00");
+    let (hours, minutes) = time.split_once(':').ok_or_else(error)?;
+    let hours: u32 = hours.parse().map_err(|_| error())?;
+    let minutes: u32 = minutes.parse().map_err(|_| error())?;
+    let time = hours * 60 + minutes;
+
+    if minutes >= 60 || time > MINUTES_IN_DAY {
+        return Err(error());
+    }
+
+    Ok(time)
+}
+
+fn parse_days(days: &str) -> Result<[bool; 7], String> {
+    let mut parsed_days = [false; 7];
+    for part in days.split(',') {
+        if let Some((from, to)) = part.split_once('-') {
+            // Ranges can go over the end of the week, e.g. Sat-Mon
+            let mut day = parse_weekday(from)?;
+            let to = parse_weekday(to)?;
+            parsed_days[day.num_days_from_monday() as usize] = true;
+            while day != to {
+                day = day.succ();
+                parsed_days[day.num_days_from_monday() as usize] = true;
+            }
+        } else {
+            parsed_days[parse_weekday(part)?.num_days_from_monday() as usize] = true;
+        }
+    }
+
+    Ok(parsed_days)
+}
+
+impl FromStr for DeployWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (days, times) = match s.trim().split_once(' ') {
+            Some((days, times)) => (parse_days(days.trim())?, times.trim()),
+            None => ([true; 7], s.trim()),
+        };
+        let (start, end) = times
+            .split_once('-')
+            .ok_or_else(|| format!("cannot parse {s}, use e.g. \"Mon-Fri 09:00-16:00\""))?;
+        let start = parse_time(start)? % MINUTES_IN_DAY;
+        let end = parse_time(end)?;
+
+        if start == end {
+            return Err(format!("cannot parse {s}, the window is empty"));
+        }
+
+        Ok(DeployWindow {
+            source: s.trim().to_string(),
+            days,
+            start,
+            end,
+        })
+    }
+}
+
+impl Display for DeployWindow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl DeployWindow {
+    fn is_on(&self, day: Weekday) -> bool {
+        self.days[day.num_days_from_monday() as usize]
+    }
+
+    /// Check if the window contains the minute of the day on the given day.
+    fn contains(&self, day: Weekday, minute: u32) -> bool {
+        if self.start < self.end {
+            self.is_on(day) && self.start <= minute && minute < self.end
+        } else {
+            (self.is_on(day) && self.start <= minute)
+                || (self.is_on(day.pred()) && minute < self.end)
+        }
+    }
+}
+
+/// The windows when updates can be applied, in the given timezone.
+///
+/// Outside of the windows the updates are held back, until one of them opens.
+#[derive(Debug, Clone)]
+pub struct DeployWindows {
+    windows: Vec<DeployWindow>,
+    timezone: Option<Tz>,
+}
+
+impl DeployWindows {
+    /// Create the windows with a timezone (e.g. "Europe/Berlin"). By default the local timezone is used.
+    pub fn new(windows: Vec<DeployWindow>, timezone: Option<&str>) -> Result<Self, GitError> {
+        let timezone = timezone
+            .map(|timezone| {
+                Tz::from_str(timezone).map_err(|_| GitError::InvalidTimezone(timezone.to_string()))
+            })
+            .transpose()?;
+
+        Ok(Self { windows, timezone })
+    }
+
+    fn is_open_in<T: TimeZone>(&self, time: DateTime<T>) -> bool {
+        let minute = time.hour() * 60 + time.minute();
+        self.windows
+            .iter()
+            .any(|window| window.contains(time.weekday(), minute))
+    }
+
+    /// Check if any of the windows is open at the given time.
+    pub fn is_open(&self, time: DateTime<Utc>) -> bool {
+        match self.timezone {
+            Some(timezone) => self.is_open_in(time.with_timezone(&timezone)),
+            None => self.is_open_in(time.with_timezone(&Local)),
+        }
+    }
+
+    /// Find the next time after the given time, when a window opens.
+    ///
+    /// Returns `None` if the windows never change, e.g. they are always open.
+    pub fn find_next_opening(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        // Go through every minute of the next week, this works with timezone changes as well
+        let first_minute = time.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut was_open = self.is_open(first_minute - Duration::minutes(1));
+        for minute in 0..=(8 * MINUTES_IN_DAY as i64) {
+            let time = first_minute + Duration::minutes(minute);
+            let is_open = self.is_open(time);
+            if is_open && !was_open {
+                return Some(time);
+            }
+            was_open = is_open;
+        }
+
+        None
+    }
+}
+
+impl Display for DeployWindows {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let windows: Vec<String> = self.windows.iter().map(|w| w.to_string()).collect();
+        f.write_str(&windows.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn it_should_parse_deploy_windows() {
+        assert!("Mon-Fri 09:00-16:00".parse::<DeployWindow>().is_ok());
+        assert!("Sat,Sun 22:00-02:00".parse::<DeployWindow>().is_ok());
+        assert!("Fri-Mon,Wed 00:00-24:00".parse::<DeployWindow>().is_ok());
+        assert!("09:00-16:00".parse::<DeployWindow>().is_ok());
+
+        assert!("Mon-Fri".parse::<DeployWindow>().is_err());
+        assert!("Someday 09:00-16:00".parse::<DeployWindow>().is_err());
+        assert!("Mon 9-16".parse::<DeployWindow>().is_err());
+        assert!("Mon 09:00-25:00".parse::<DeployWindow>().is_err());
+        assert!("Mon 09:00-09:00".parse::<DeployWindow>().is_err());
+    }
+
+    #[test]
+    fn it_should_check_if_the_deploy_windows_are_open() -> Result<(), Box<dyn Error>> {
+        let windows = DeployWindows::new(
+            vec!["Mon-Thu 09:00-16:00".parse()?, "Sat 22:00-02:00".parse()?],
+            Some("UTC"),
+        )?;
+
+        // 2026-10-15 is a Thursday
+        assert!(windows.is_open(Utc.with_ymd_and_hms(2026, 10, 15, 9, 0, 0).unwrap()));
+        assert!(windows.is_open(Utc.with_ymd_and_hms(2026, 10, 15, 15, 59, 0).unwrap()));
+        assert!(!windows.is_open(Utc.with_ymd_and_hms(2026, 10, 15, 16, 0, 0).unwrap()));
+        assert!(!windows.is_open(Utc.with_ymd_and_hms(2026, 10, 15, 8, 59, 0).unwrap()));
+        assert!(!windows.is_open(Utc.with_ymd_and_hms(2026, 10, 16, 10, 0, 0).unwrap()));
+
+        // The window on Saturday should go over midnight to Sunday
+        assert!(windows.is_open(Utc.with_ymd_and_hms(2026, 10, 17, 23, 0, 0).unwrap()));
+        assert!(windows.is_open(Utc.with_ymd_and_hms(2026, 10, 18, 1, 0, 0).unwrap()));
+        assert!(!windows.is_open(Utc.with_ymd_and_hms(2026, 10, 18, 23, 0, 0).unwrap()));
+
+        // It should use the timezone
+        let windows = DeployWindows::new(vec!["09:00-16:00".parse()?], Some("Europe/Berlin"))?;
+        assert!(windows.is_open(Utc.with_ymd_and_hms(2026, 10, 15, 7, 0, 0).unwrap()));
+        assert!(!windows.is_open(Utc.with_ymd_and_hms(2026, 10, 15, 14, 30, 0).unwrap()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_find_the_next_deploy_window_opening() -> Result<(), Box<dyn Error>> {
+        let windows = DeployWindows::new(vec!["Mon-Thu 09:00-16:00".parse()?], Some("UTC"))?;
+
+        // On Thursday afternoon, it should wait until Monday morning
+        let next =
+            windows.find_next_opening(Utc.with_ymd_and_hms(2026, 10, 15, 17, 30, 0).unwrap());
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2026, 10, 19, 9, 0, 0).unwrap()),
+            next
+        );
+
+        // In an open window, it should find the next day
+        let next = windows.find_next_opening(Utc.with_ymd_and_hms(2026, 10, 13, 9, 0, 0).unwrap());
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2026, 10, 14, 9, 0, 0).unwrap()),
+            next
+        );
+
+        // If the window is always open, there is no opening
+        let windows = DeployWindows::new(vec!["00:00-24:00".parse()?], Some("UTC"))?;
+        assert_eq!(None, windows.find_next_opening(Utc::now()));
+
+        Ok(())
+    }
+}
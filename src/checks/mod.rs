@@ -1,5 +1,6 @@
 use crate::context::Context;
 use mockall::automock;
+use std::path::Path;
 use thiserror::Error;
 
 /// A check to fetch and pull a local git repository.
@@ -35,4 +36,18 @@ pub enum CheckError {
 pub trait Check {
     /// Check if there are changes and update if necessary.
     fn check(&mut self, context: &mut Context) -> Result<bool, CheckError>;
+
+    /// Roll back the last update, because the actions failed.
+    ///
+    /// Returns false if the check cannot roll back (e.g. watching a directory).
+    fn rollback(&mut self, _context: &mut Context) -> Result<bool, CheckError> {
+        Ok(false)
+    }
+
+    /// Write the updated files to another directory (e.g. a new release), without changing the checked out files.
+    ///
+    /// Returns false if the check cannot export (e.g. watching a directory).
+    fn export(&mut self, _context: &Context, _directory: &Path) -> Result<bool, CheckError> {
+        Ok(false)
+    }
 }
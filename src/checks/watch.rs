@@ -1,15 +1,437 @@
 use super::{Check, CheckError};
-use crate::context::Context;
-use std::result::Result;
+use crate::{
+    context::Context,
+    paths::{PathFilter, PathFilterError},
+};
+use log::{debug, trace};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    result::Result,
+    time::SystemTime,
+};
+use thiserror::Error;
+
+const CHECK_NAME: &str = "WATCH";
+
+/// The state of a file, if it changes the file was modified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileState {
+    modified: Option<SystemTime>,
+    size: u64,
+}
+
+type Snapshot = HashMap<String, FileState>;
 
 /// A check to watch a directory for changes.
-pub struct WatchCheck;
+///
+/// This compares the files with the previous check, and reports the added, modified or deleted files.
+pub struct WatchCheck {
+    directory: PathBuf,
+    ignores: PathFilter,
+    snapshot: Snapshot,
+}
+
+/// A custom error describing the error cases for the WatchCheck.
+#[derive(Debug, Error)]
+pub enum WatchError {
+    /// The path is not a directory.
+    #[error("{0} is not a directory")]
+    NotADirectory(String),
+    /// The ignore pattern is not a valid glob.
+    #[error("ignore pattern {0} is invalid: {1}")]
+    InvalidIgnorePattern(String, String),
+    /// Cannot read the directory or the file.
+    #[error("cannot read {0} ({1})")]
+    FailedReading(String, io::Error),
+}
+
+impl From<PathFilterError> for WatchError {
+    fn from(value: PathFilterError) -> Self {
+        match value {
+            PathFilterError::InvalidPattern(pattern, err) => {
+                WatchError::InvalidIgnorePattern(pattern, err)
+            }
+        }
+    }
+}
+
+impl From<WatchError> for CheckError {
+    fn from(value: WatchError) -> Self {
+        match value {
+            WatchError::NotADirectory(_) | WatchError::InvalidIgnorePattern(_, _) => {
+                CheckError::Misconfigured(value.to_string())
+            }
+            WatchError::FailedReading(_, _) => CheckError::PermissionDenied(value.to_string()),
+        }
+    }
+}
+
+/// The changes between two snapshots.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Changes {
+    added: BTreeSet<String>,
+    modified: BTreeSet<String>,
+    deleted: BTreeSet<String>,
+}
+
+impl Changes {
+    fn between(previous: &Snapshot, current: &Snapshot) -> Self {
+        let mut changes = Changes::default();
+        for (path, state) in current {
+            match previous.get(path) {
+                None => {
+                    changes.added.insert(path.clone());
+                }
+                Some(previous_state) if previous_state != state => {
+                    changes.modified.insert(path.clone());
+                }
+                Some(_) => {}
+            }
+        }
+        for path in previous.keys() {
+            if !current.contains_key(path) {
+                changes.deleted.insert(path.clone());
+            }
+        }
+
+        changes
+    }
+
+    fn all(&self) -> BTreeSet<&String> {
+        self.added
+            .iter()
+            .chain(&self.modified)
+            .chain(&self.deleted)
+            .collect()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.deleted.is_empty()
+    }
+}
+
+fn join_paths<'a>(paths: impl IntoIterator<Item = &'a String>) -> String {
+    paths
+        .into_iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl WatchCheck {
+    /// Open the directory to watch, and save the current state of the files.
+    pub fn open(directory: &str) -> Result<Self, CheckError> {
+        let directory = PathBuf::from(directory);
+        if !directory.is_dir() {
+            return Err(WatchError::NotADirectory(directory.to_string_lossy().to_string()).into());
+        }
+
+        let mut watch_check = WatchCheck {
+            directory,
+            ignores: PathFilter::default(),
+            snapshot: HashMap::new(),
+        };
+        watch_check.snapshot = watch_check.read_snapshot()?;
+
+        Ok(watch_check)
+    }
+
+    /// Ignore the files matching these globs, with the same syntax as the path filters. If the pattern
+    /// doesn't contain a `/`, it is matched against the file name (e.g. `*.log`), otherwise the relative path (e.g. `logs/*`).
+    pub fn set_ignores(&mut self, ignores: Vec<String>) -> Result<(), CheckError> {
+        self.ignores = PathFilter::new(&[], &ignores).map_err(WatchError::from)?;
+
+        // The previous state might contain the newly ignored files
+        self.snapshot = self.read_snapshot()?;
+
+        Ok(())
+    }
+
+    fn read_directory(&self, path: &Path, snapshot: &mut Snapshot) -> Result<(), WatchError> {
+        // The subdirectories might be deleted while reading them (e.g. replaced by rsync)
+        let failed_reading = |err: io::Error| {
+            if err.kind() == io::ErrorKind::NotFound && path != self.directory {
+                trace!("Skipping {}, it was deleted.", path.display());
+                return Ok(());
+            }
+            Err(WatchError::FailedReading(
+                path.to_string_lossy().to_string(),
+                err,
+            ))
+        };
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(err) => return failed_reading(err),
+        };
+
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => return failed_reading(err),
+            };
+            let entry_path = entry.path();
+            let relative_path = entry_path
+                .strip_prefix(&self.directory)
+                .unwrap_or(&entry_path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            if !self.ignores.is_empty() && !self.ignores.matches(&relative_path) {
+                trace!("Ignoring {relative_path}.");
+                continue;
+            }
+
+            // Symlinks are not followed, to avoid loops
+            let metadata = match entry_path.symlink_metadata() {
+                Ok(metadata) => metadata,
+                // The file might have been deleted since reading the directory
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(WatchError::FailedReading(relative_path, err)),
+            };
+
+            if metadata.is_dir() {
+                self.read_directory(&entry_path, snapshot)?;
+            } else {
+                snapshot.insert(
+                    relative_path,
+                    FileState {
+                        modified: metadata.modified().ok(),
+                        size: metadata.len(),
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_snapshot(&self) -> Result<Snapshot, WatchError> {
+        let mut snapshot = HashMap::new();
+        self.read_directory(&self.directory, &mut snapshot)?;
+
+        Ok(snapshot)
+    }
+
+    fn check_inner(&mut self, context: &mut Context) -> Result<bool, WatchError> {
+        context.insert("CHECK_NAME", CHECK_NAME.to_string());
+        context.insert(
+            "WATCH_DIRECTORY",
+            self.directory.to_string_lossy().to_string(),
+        );
+
+        let snapshot = self.read_snapshot()?;
+        let changes = Changes::between(&self.snapshot, &snapshot);
+        self.snapshot = snapshot;
+
+        if changes.is_empty() {
+            return Ok(false);
+        }
+
+        debug!(
+            "There are {} added, {} modified and {} deleted files.",
+            changes.added.len(),
+            changes.modified.len(),
+            changes.deleted.len()
+        );
+        let changed_files = changes.all();
+        context.insert("WATCH_CHANGED_COUNT", changed_files.len().to_string());
+        context.insert("WATCH_CHANGED_FILES", join_paths(changed_files));
+        context.insert("WATCH_ADDED_FILES", join_paths(&changes.added));
+        context.insert("WATCH_MODIFIED_FILES", join_paths(&changes.modified));
+        context.insert("WATCH_DELETED_FILES", join_paths(&changes.deleted));
+
+        Ok(true)
+    }
+}
 
 impl Check for WatchCheck {
-    fn check(&mut self, _context: &mut Context) -> Result<bool, CheckError> {
-        todo!()
+    /// Compare the files in the directory with the previous check.
+    /// It returns true if any file was added, modified or deleted.
+    fn check(&mut self, context: &mut Context) -> Result<bool, CheckError> {
+        let has_changed = self.check_inner(context)?;
+
+        Ok(has_changed)
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use rand::distr::{Alphanumeric, SampleString};
+    use std::{error::Error, thread::sleep, time::Duration};
+
+    fn get_random_id() -> String {
+        Alphanumeric.sample_string(&mut rand::rng(), 16)
+    }
+
+    fn create_directory(local: &str) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(format!("{local}/sub"))?;
+        fs::write(format!("{local}/1"), "1")?;
+        fs::write(format!("{local}/sub/2"), "2")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fail_if_path_is_not_a_directory() {
+        let error = WatchCheck::open("/path/to/nowhere").err().unwrap();
+
+        assert!(
+            matches!(error, CheckError::Misconfigured(_)),
+            "{error:?} should be Misconfigured"
+        );
+    }
+
+    #[test]
+    fn it_should_return_false_if_nothing_changed() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        create_directory(&local)?;
+
+        let mut check = WatchCheck::open(&local)?;
+        let mut context: Context = HashMap::new();
+        assert!(!check.check(&mut context)?);
+        assert_eq!(CHECK_NAME, context.get("CHECK_NAME").unwrap());
+
+        let _ = fs::remove_dir_all(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_detect_added_modified_and_deleted_files() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        create_directory(&local)?;
+
+        let mut check = WatchCheck::open(&local)?;
+
+        fs::write(format!("{local}/sub/3"), "3")?;
+        fs::write(format!("{local}/1"), "11")?;
+        fs::remove_file(format!("{local}/sub/2"))?;
+
+        let mut context: Context = HashMap::new();
+        assert!(check.check(&mut context)?);
+        assert_eq!("3", context.get("WATCH_CHANGED_COUNT").unwrap());
+        assert_eq!(
+            "1\nsub/2\nsub/3",
+            context.get("WATCH_CHANGED_FILES").unwrap()
+        );
+        assert_eq!("sub/3", context.get("WATCH_ADDED_FILES").unwrap());
+        assert_eq!("1", context.get("WATCH_MODIFIED_FILES").unwrap());
+        assert_eq!("sub/2", context.get("WATCH_DELETED_FILES").unwrap());
+
+        // The next check should only compare with the last one
+        let mut context: Context = HashMap::new();
+        assert!(!check.check(&mut context)?);
+
+        let _ = fs::remove_dir_all(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_detect_modification_with_the_same_size() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        create_directory(&local)?;
+
+        let mut check = WatchCheck::open(&local)?;
+
+        // Wait for the modification time to be different
+        sleep(Duration::from_millis(10));
+        fs::write(format!("{local}/1"), "2")?;
+
+        let mut context: Context = HashMap::new();
+        assert!(check.check(&mut context)?);
+        assert_eq!("1", context.get("WATCH_MODIFIED_FILES").unwrap());
+
+        let _ = fs::remove_dir_all(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_not_detect_ignored_files() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        create_directory(&local)?;
+
+        let mut check = WatchCheck::open(&local)?;
+        check.set_ignores(vec![
+            String::from("*.log"),
+            String::from("sub/"),
+            String::from("cache/*.tmp"),
+        ])?;
+
+        fs::create_dir_all(format!("{local}/cache"))?;
+        fs::write(format!("{local}/server.log"), "log")?;
+        fs::write(format!("{local}/sub/3"), "3")?;
+        fs::write(format!("{local}/cache/file.tmp"), "tmp")?;
+
+        let mut context: Context = HashMap::new();
+        assert!(!check.check(&mut context)?);
+
+        // Files not matching the ignores should be detected
+        fs::write(format!("{local}/cache/file.json"), "{}")?;
+
+        let mut context: Context = HashMap::new();
+        assert!(check.check(&mut context)?);
+        assert_eq!(
+            "cache/file.json",
+            context.get("WATCH_CHANGED_FILES").unwrap()
+        );
+
+        let _ = fs::remove_dir_all(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_skip_the_deleted_subdirectories() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        create_directory(&local)?;
+
+        let check = WatchCheck::open(&local)?;
+
+        // The subdirectory might be deleted between reading its parent and itself
+        let mut snapshot = HashMap::new();
+        fs::remove_dir_all(format!("{local}/sub"))?;
+        check.read_directory(&PathBuf::from(format!("{local}/sub")), &mut snapshot)?;
+        assert!(snapshot.is_empty());
+
+        // The watched directory should still fail
+        fs::remove_dir_all(&local)?;
+        let result = check.read_snapshot();
+        assert!(
+            matches!(result, Err(WatchError::FailedReading(_, _))),
+            "{result:?} should be FailedReading"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fail_on_invalid_ignore_pattern() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        create_directory(&local)?;
+
+        let mut check = WatchCheck::open(&local)?;
+        let error = check.set_ignores(vec![String::from("[")]).err().unwrap();
+
+        assert!(
+            matches!(error, CheckError::Misconfigured(_)),
+            "{error:?} should be Misconfigured"
+        );
+
+        let _ = fs::remove_dir_all(&local);
+
+        Ok(())
+    }
+}
@@ -1,3 +1,185 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime,
+};
 
 pub type Context = HashMap<&'static str, String>;
+
+/// The longest value that is passed directly in an environment variable.
+///
+/// Linux limits every variable to 128 KiB, so we stay well below that.
+pub const MAX_VARIABLE_LENGTH: usize = 32 * 1024;
+
+/// A private temporary directory for the files passed to commands (e.g. the long values),
+/// that is removed when dropped.
+///
+/// The directory is only created when a file is written, with a unique name and
+/// only accessible by the current user. If the path exists already, another name is tried,
+/// so the files can never be written to a directory prepared by someone else.
+#[derive(Debug)]
+pub struct TempDirectory {
+    parent: PathBuf,
+    path: Option<PathBuf>,
+}
+
+impl TempDirectory {
+    /// Create the directory in the temporary directory of the system.
+    pub fn new() -> Self {
+        Self::new_in(env::temp_dir())
+    }
+
+    /// Create the directory in the parent directory.
+    pub fn new_in(parent: impl AsRef<Path>) -> Self {
+        TempDirectory {
+            parent: parent.as_ref().to_path_buf(),
+            path: None,
+        }
+    }
+
+    /// The path of the directory, if it was created.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Write the file to the directory, and return its path.
+    pub fn write(&mut self, name: &str, contents: impl AsRef<[u8]>) -> io::Result<PathBuf> {
+        let path = self.create()?.join(name);
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    fn create(&mut self) -> io::Result<PathBuf> {
+        if let Some(path) = &self.path {
+            return Ok(path.clone());
+        }
+
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        fs::create_dir_all(&self.parent)?;
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+
+        let mut attempts = 0;
+        loop {
+            let nanos = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|duration| duration.subsec_nanos())
+                .unwrap_or_default();
+            let name = format!(
+                "gw-{}-{}-{nanos}",
+                process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            );
+            let path = self.parent.join(name);
+            match builder.create(&path) {
+                Ok(()) => {
+                    self.path = Some(path.clone());
+                    return Ok(path);
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists && attempts < 10 => {
+                    attempts += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl Default for TempDirectory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TempDirectory {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = fs::remove_dir_all(path);
+        }
+    }
+}
+
+/// Convert the context to environment variables prefixed with `GW_`.
+///
+/// The values longer than [MAX_VARIABLE_LENGTH] are written to a file in the directory,
+/// and the path is passed in a variable with a `_FILE` suffix instead (e.g. `GW_GIT_CHANGED_FILES_FILE`).
+/// The files are removed with the directory, so it has to live until the command finished.
+pub fn to_variables(
+    context: &Context,
+    directory: &mut TempDirectory,
+) -> io::Result<Vec<(String, String)>> {
+    context
+        .iter()
+        .map(|(key, value)| {
+            if value.len() <= MAX_VARIABLE_LENGTH {
+                return Ok((format!("GW_{key}"), value.clone()));
+            }
+
+            let path = directory.write(key, value)?;
+            Ok((format!("GW_{key}_FILE"), path.to_string_lossy().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::distr::{Alphanumeric, SampleString};
+
+    #[test]
+    fn it_should_prefix_the_variables() -> io::Result<()> {
+        let context: Context = HashMap::from([("CHECK_NAME", String::from("GIT"))]);
+
+        let mut directory = TempDirectory::new_in("test_directories/nowhere");
+        let variables = to_variables(&context, &mut directory)?;
+        assert_eq!(
+            vec![(String::from("GW_CHECK_NAME"), String::from("GIT"))],
+            variables
+        );
+        assert_eq!(None, directory.path());
+        assert!(!Path::new("test_directories/nowhere").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_write_long_values_to_private_files() -> io::Result<()> {
+        let id = Alphanumeric.sample_string(&mut rand::rng(), 16);
+        let parent = format!("test_directories/{id}");
+        let changed_files = "file\n".repeat(MAX_VARIABLE_LENGTH);
+        let context: Context = HashMap::from([("GIT_CHANGED_FILES", changed_files.clone())]);
+
+        let mut directory = TempDirectory::new_in(&parent);
+        let variables = to_variables(&context, &mut directory)?;
+        let path = directory.path().unwrap().join("GIT_CHANGED_FILES");
+        assert_eq!(
+            vec![(
+                String::from("GW_GIT_CHANGED_FILES_FILE"),
+                path.to_string_lossy().to_string()
+            )],
+            variables
+        );
+        assert_eq!(changed_files, fs::read_to_string(&path)?);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(directory.path().unwrap())?
+                .permissions()
+                .mode();
+            assert_eq!(0o700, mode & 0o777);
+        }
+
+        // The files should be removed with the directory
+        drop(directory);
+        assert!(!path.exists());
+
+        let _ = fs::remove_dir_all(&parent);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{
+    context::Context,
+    start::{ActionReport, RunReport},
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// The name of the file in the state directory, where the runs are appended.
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// A custom error for reading and writing the history.
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("cannot create state directory {0}: {1}")]
+    StateDirectoryFailed(String, io::Error),
+    #[error("cannot write history to {0}: {1}")]
+    WriteFailed(String, io::Error),
+    #[error("cannot read history from {0}: {1}")]
+    ReadFailed(String, io::Error),
+}
+
+/// A run of the pipeline, that is saved to the history.
+#[derive(Debug, Clone)]
+pub struct RunRecord {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub trigger: Option<String>,
+    pub check: Option<String>,
+    pub ref_name: Option<String>,
+    pub before_commit_sha: Option<String>,
+    pub report: RunReport,
+}
+
+impl RunRecord {
+    /// Create a record from the context and the report of the run.
+    pub fn new(
+        context: &Context,
+        report: RunReport,
+        started_at: DateTime<Utc>,
+        finished_at: DateTime<Utc>,
+    ) -> Self {
+        RunRecord {
+            started_at,
+            finished_at,
+            trigger: context.get("TRIGGER_NAME").cloned(),
+            check: context.get("CHECK_NAME").cloned(),
+            ref_name: context.get("GIT_REF_NAME").cloned(),
+            before_commit_sha: context.get("GIT_BEFORE_COMMIT_SHA").cloned(),
+            report,
+        }
+    }
+
+    /// Convert the record to a JSON value.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "started_at": self.started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "finished_at": self.finished_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "trigger": self.trigger,
+            "check": self.check,
+            "ref": self.ref_name,
+            "before_commit_sha": self.before_commit_sha,
+            "commit_sha": self.report.commit_sha,
+            "success": self.report.is_success(),
+            "rolled_back": self.report.rolled_back,
+            "actions": self
+                .report
+                .actions
+                .iter()
+                .map(ActionReport::to_json)
+                .collect::<Vec<_>>(),
+            "error": self.report.error,
+        })
+    }
+}
+
+/// The deploy history, saved as JSON lines in the state directory.
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    /// Create the history in the state directory, without touching the filesystem.
+    pub fn new(state_directory: impl AsRef<Path>) -> Self {
+        History {
+            path: state_directory.as_ref().join(HISTORY_FILE),
+        }
+    }
+
+    /// Open the history in the state directory, creating the directory if it doesn't exist.
+    pub fn open(state_directory: impl AsRef<Path>) -> Result<Self, HistoryError> {
+        let state_directory = state_directory.as_ref();
+        fs::create_dir_all(state_directory).map_err(|err| {
+            HistoryError::StateDirectoryFailed(state_directory.display().to_string(), err)
+        })?;
+
+        Ok(History::new(state_directory))
+    }
+
+    /// The path of the history file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a run to the end of the history.
+    pub fn append(&self, record: &RunRecord) -> Result<(), HistoryError> {
+        let write_failed = |err| HistoryError::WriteFailed(self.path.display().to_string(), err);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(write_failed)?;
+        writeln!(file, "{}", record.to_json()).map_err(write_failed)?;
+
+        Ok(())
+    }
+
+    /// Read the last runs from the history, oldest first.
+    ///
+    /// The lines that cannot be parsed (e.g. a partially written last line) are skipped.
+    pub fn read(&self, limit: usize) -> Result<Vec<Value>, HistoryError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => {
+                return Err(HistoryError::ReadFailed(
+                    self.path.display().to_string(),
+                    err,
+                ))
+            }
+        };
+
+        let records: Vec<Value> = content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        let skip = records.len().saturating_sub(limit);
+
+        Ok(records.into_iter().skip(skip).collect())
+    }
+}
+
+/// Format a run from the history in a single line (e.g. for `gw history`).
+pub fn format_record(record: &Value) -> String {
+    let short = |key: &str| -> String {
+        record[key]
+            .as_str()
+            .unwrap_or("-")
+            .chars()
+            .take(7)
+            .collect()
+    };
+    let status = if record["rolled_back"] == true {
+        "rolled back"
+    } else if record["success"] == true {
+        "success"
+    } else {
+        "failed"
+    };
+    let actions: Vec<String> = record["actions"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|action| {
+            let name = action["name"].as_str().unwrap_or("-");
+            if action["skipped"] == true {
+                return format!("{name}: skipped");
+            }
+            let exit_code = action["exit_code"]
+                .as_i64()
+                .map_or(String::from("-"), |code| code.to_string());
+            let duration = action["duration_ms"].as_u64().unwrap_or_default() as f64 / 1000.0;
+            format!("{name}: exit {exit_code} in {duration:.1}s")
+        })
+        .collect();
+
+    // Without actions, show the error of the check instead
+    let details = match (actions.is_empty(), record["error"].as_str()) {
+        (false, _) => format!(" ({})", actions.join(", ")),
+        (true, Some(error)) => format!(" ({error})"),
+        (true, None) => String::new(),
+    };
+
+    format!(
+        "{} {} {}..{} {} {status}{details}",
+        record["started_at"].as_str().unwrap_or("-"),
+        record["trigger"].as_str().unwrap_or("-"),
+        short("before_commit_sha"),
+        short("commit_sha"),
+        record["ref"].as_str().unwrap_or("-"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::distr::{Alphanumeric, SampleString};
+    use std::{collections::HashMap, error::Error, time::Duration};
+
+    fn create_record(commit_sha: &str) -> RunRecord {
+        let context: Context = HashMap::from([
+            ("TRIGGER_NAME", String::from("SCHEDULE")),
+            ("CHECK_NAME", String::from("GIT")),
+            ("GIT_REF_NAME", String::from("refs/heads/main")),
+            ("GIT_BEFORE_COMMIT_SHA", String::from("abc123")),
+        ]);
+        let report = RunReport {
+            updated: true,
+            commit_sha: Some(commit_sha.to_string()),
+            actions: vec![ActionReport {
+                name: String::from("false"),
+                exit_code: Some(1),
+                duration: Duration::from_millis(1500),
+                error: Some(String::from("the script returned non-zero exit code 1")),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        RunRecord::new(&context, report, Utc::now(), Utc::now())
+    }
+
+    #[test]
+    fn it_should_append_and_read_runs() -> Result<(), Box<dyn Error>> {
+        let id = Alphanumeric.sample_string(&mut rand::rng(), 16);
+        let directory = format!("test_directories/{id}");
+        let history = History::open(&directory)?;
+        assert!(history.read(10)?.is_empty());
+
+        history.append(&create_record("def456"))?;
+        history.append(&create_record("ghi789"))?;
+
+        let records = history.read(10)?;
+        assert_eq!(2, records.len());
+        assert_eq!("SCHEDULE", records[0]["trigger"]);
+        assert_eq!("refs/heads/main", records[0]["ref"]);
+        assert_eq!("abc123", records[0]["before_commit_sha"]);
+        assert_eq!("def456", records[0]["commit_sha"]);
+        assert_eq!(false, records[0]["success"]);
+        assert_eq!(1, records[0]["actions"][0]["exit_code"]);
+        assert_eq!(1500, records[0]["actions"][0]["duration_ms"]);
+
+        let records = history.read(1)?;
+        assert_eq!(1, records.len());
+        assert_eq!("ghi789", records[0]["commit_sha"]);
+        assert!(format_record(&records[0])
+            .ends_with("SCHEDULE abc123..ghi789 refs/heads/main failed (false: exit 1 in 1.5s)"));
+
+        // The failed checks should show the error
+        let mut record = create_record("ghi789");
+        record.report.actions = vec![];
+        record.report.error = Some(String::from("cannot fetch"));
+        assert!(format_record(&record.to_json()).ends_with("failed (cannot fetch)"));
+
+        fs::remove_dir_all(&directory)?;
+
+        Ok(())
+    }
+}
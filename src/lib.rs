@@ -30,3 +30,12 @@ pub mod start;
 
 /// The context which can share data between the different steps.
 pub mod context;
+
+/// Path filters to decide which changed files are relevant.
+pub mod paths;
+
+/// The deploy history, which saves every update to the state directory.
+pub mod history;
+
+/// Release directories, which check out every update separately and switch a symlink to it.
+pub mod releases;
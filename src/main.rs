@@ -1,4 +1,6 @@
-use args::{parse_args, ArgAction};
+use args::{
+    is_history_command, parse_args, parse_history_args, ArgAction, CheckArgument, TriggerArgument,
+};
 use gw_bin::{
     actions::{
         process::{ProcessAction, ProcessParams},
@@ -6,18 +8,30 @@ use gw_bin::{
         Action, ActionError,
     },
     checks::{
-        git::{CredentialAuth, GitCheck},
+        git::{
+            CredentialAuth, DeployWindows, GitCheck, GitTarget, GitTriggerArgument,
+            SignatureVerifier, SkipDirectives,
+        },
+        watch::WatchCheck,
         Check, CheckError,
     },
-    start::{start, StartError},
+    history::{format_record, History, HistoryError},
+    paths::{PathFilter, PathFilterError},
+    releases::Releases,
+    start::{start_with_options, RollbackPolicy, StartError, StartOptions},
     triggers::{
-        http::HttpTrigger, once::OnceTrigger, schedule::ScheduleTrigger, signal::SignalTrigger,
+        file::FileTrigger,
+        http::HttpTrigger,
+        once::OnceTrigger,
+        schedule::{CronSchedule, ScheduleError, ScheduleTrigger},
+        signal::SignalTrigger,
+        window::WindowTrigger,
         Trigger,
     },
 };
 use log::{debug, error, warn, SetLoggerError};
 use logger::init_logger;
-use std::{fs, process, time::Duration};
+use std::{fs, path, process, time::Duration};
 use thiserror::Error;
 
 mod args;
@@ -31,6 +45,20 @@ pub enum MainError {
     NonExistentDirectory(String),
     #[error("You cannot start multiple processes, only add -p or -P once.")]
     MultipleProcessArgs,
+    #[error(
+        "You have to set the branch to follow with --branch, when using --detached on pushes."
+    )]
+    DetachedWithoutBranch,
+    #[error("The release directory {0} has to be outside of the repository.")]
+    ReleaseDirInRepository(String),
+    #[error("You have to pass both --http-tls-cert and --http-tls-key to serve HTTPS.")]
+    IncompleteTlsArgs,
+    #[error("You have to add --when-changed after a script or a process.")]
+    WhenChangedWithoutAction,
+    #[error("Schedule is invalid: {0}.")]
+    InvalidSchedule(#[from] ScheduleError),
+    #[error("Path filter is invalid: {0}.")]
+    InvalidPathFilter(#[from] PathFilterError),
     #[error("Check failed: {0}.")]
     FailedCheck(#[from] CheckError),
     #[error("Failed setting up logger with timezones.")]
@@ -41,6 +69,10 @@ pub enum MainError {
     FailedStart(#[from] StartError),
     #[error("Action failed: {0}.")]
     FailedAction(#[from] ActionError),
+    #[error("You have to pass the state directory with --state-dir.")]
+    MissingStateDirArg,
+    #[error("History failed: {0}.")]
+    FailedHistory(#[from] HistoryError),
 }
 
 fn main_inner() -> Result<(), MainError> {
@@ -55,6 +87,10 @@ fn main_inner() -> Result<(), MainError> {
 
     // Check if directory exists and convert to full path
     let directory_relative = args.directory.ok_or(MainError::MissingDirectoryArg)?;
+    if args.clone.is_some() {
+        fs::create_dir_all(&directory_relative)
+            .map_err(|_| MainError::NonExistentDirectory(directory_relative.clone()))?;
+    }
     let directory_path = fs::canonicalize(directory_relative.clone())
         .map_err(|_| MainError::NonExistentDirectory(directory_relative.clone()))?;
     let directory = directory_path
@@ -62,6 +98,140 @@ fn main_inner() -> Result<(), MainError> {
         .ok_or(MainError::NonExistentDirectory(directory_relative))?
         .to_string();
 
+    // Setup releases.
+    let mut releases = match args.release_dir {
+        Some(release_dir) => {
+            // Check before creating it, so the repository is not left with a new directory
+            let release_path = path::absolute(&release_dir)
+                .map_err(|_| MainError::NonExistentDirectory(release_dir.clone()))?;
+            if release_path.starts_with(&directory_path) {
+                return Err(MainError::ReleaseDirInRepository(release_dir));
+            }
+            fs::create_dir_all(&release_path)
+                .map_err(|_| MainError::NonExistentDirectory(release_dir.clone()))?;
+            let release_path = fs::canonicalize(&release_path)
+                .map_err(|_| MainError::NonExistentDirectory(release_dir.clone()))?;
+            if release_path.starts_with(&directory_path) {
+                return Err(MainError::ReleaseDirInRepository(release_dir));
+            }
+            debug!("Setting up releases in \"{}\".", release_path.display());
+            let mut releases = Releases::new(release_path);
+            if let Some(keep) = args.keep_releases {
+                releases.set_keep(keep);
+            }
+            Some(releases)
+        }
+        None => None,
+    };
+
+    // Setup check.
+    let follows_tags = !matches!(args.trigger, TriggerArgument::Push);
+    let (mut check, git_ref, windows): (Box<dyn Check>, _, _) = match args.check {
+        CheckArgument::Git => {
+            let mut git_trigger = args.trigger.into();
+            if let GitTriggerArgument::Semver(range) = &mut git_trigger {
+                range.set_prerelease(args.prerelease);
+            } else if args.prerelease {
+                warn!("Pre-releases are only supported with semver triggers, ignoring them.");
+            }
+            debug!("Setting up GitCheck for \"{directory}\" on every {git_trigger}.");
+            let auth = match (args.git_username, args.git_token) {
+                (Some(username), Some(password)) => Some(CredentialAuth::Https(username, password)),
+                _ => args.ssh_key.map(CredentialAuth::Ssh),
+            };
+            if args.detached
+                && args.branch.is_none()
+                && matches!(git_trigger, GitTriggerArgument::Push)
+            {
+                return Err(MainError::DetachedWithoutBranch);
+            }
+            let target = GitTarget {
+                remote: args.remote,
+                branch: args.branch,
+                detached: args.detached,
+            };
+            let is_empty = fs::read_dir(&directory_path)
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(false);
+            let mut git_check = match args.clone {
+                Some(url) if is_empty => GitCheck::clone(
+                    &directory,
+                    &url,
+                    target,
+                    auth.clone(),
+                    args.git_known_host,
+                    git_trigger,
+                )?,
+                _ => GitCheck::open(&directory, target, args.git_known_host, git_trigger)?,
+            };
+            if let Some(auth) = auth {
+                git_check.set_auth(auth);
+            }
+            if releases.as_ref().is_some_and(|r| r.current().is_none()) {
+                debug!("There is no current release, deploying the checked out commit.");
+                git_check.set_initial_deploy(true);
+            }
+            let windows = if args.deploy_window.is_empty() {
+                None
+            } else {
+                let windows = DeployWindows::new(args.deploy_window, args.timezone.as_deref())
+                    .map_err(CheckError::from)?;
+                debug!("Setting up deploy windows: {windows}.");
+                git_check.set_windows(windows.clone());
+                Some(windows)
+            };
+            let paths = PathFilter::new(&args.include, &args.exclude)?;
+            if !paths.is_empty() {
+                debug!(
+                    "Setting up path filter, including [{}] and excluding [{}].",
+                    args.include.join(", "),
+                    args.exclude.join(", ")
+                );
+                git_check.set_paths(paths);
+            }
+            git_check.set_skip(SkipDirectives::new(args.skip_trailer, args.skip_scope));
+            git_check.set_mirror(args.mirror);
+            git_check.set_dirty_policy(args.dirty);
+            if !args.dirty_ignore.is_empty() {
+                git_check.set_dirty_ignores(PathFilter::new(&args.dirty_ignore, &[])?);
+            }
+            if args.allowed_signers.is_some() || !args.gpg_key.is_empty() {
+                debug!("Setting up signature verification.");
+                let verifier = SignatureVerifier::new(args.allowed_signers, args.gpg_key)
+                    .map_err(CheckError::from)?;
+                git_check.set_verifier(verifier);
+            }
+            let information = git_check
+                .repo
+                .get_repository_information()
+                .map_err(CheckError::from)?;
+            (Box::new(git_check), Some(information.ref_name), windows)
+        }
+        CheckArgument::Watch => {
+            debug!("Setting up WatchCheck for \"{directory}\".");
+            let mut watch_check = WatchCheck::open(&directory)?;
+            if !args.watch_ignore.is_empty() {
+                watch_check.set_ignores(args.watch_ignore)?;
+            }
+            if !args.deploy_window.is_empty() {
+                warn!("Deploy windows are only supported for git, ignoring them.");
+            }
+            if args.clone.is_some() {
+                warn!("Cloning is only supported for git, ignoring it.");
+            }
+            if args.rollback || args.rollback_script.is_some() {
+                warn!("Rolling back is only supported for git, ignoring it.");
+            }
+            if releases.take().is_some() {
+                warn!("Releases are only supported for git, ignoring them.");
+            }
+            if !args.include.is_empty() || !args.exclude.is_empty() {
+                warn!("Include and exclude paths are only supported for git, use --watch-ignore instead.");
+            }
+            (Box::new(watch_check), None, None)
+        }
+    };
+
     // Setup triggers.
     let mut triggers: Vec<Box<dyn Trigger>> = vec![Box::new(SignalTrigger::new())];
     if args.once {
@@ -69,28 +239,53 @@ fn main_inner() -> Result<(), MainError> {
         triggers.push(Box::new(OnceTrigger));
     } else {
         let duration: Duration = args.delay.into();
-        if !duration.is_zero() {
+        if let Some(cron) = args.cron {
+            debug!("Setting up ScheduleTrigger on cron \"{cron}\".");
+            let cron = CronSchedule::new(&cron, args.timezone.as_deref())?;
+            triggers.push(Box::new(ScheduleTrigger::new_cron(cron)));
+        } else if !duration.is_zero() {
             debug!("Setting up ScheduleTrigger on every {}.", args.delay);
             triggers.push(Box::new(ScheduleTrigger::new(duration)));
         }
+        if !args.trigger_file.is_empty() {
+            debug!(
+                "Setting up FileTrigger on {}.",
+                args.trigger_file.join(", ")
+            );
+            let mut file_trigger = FileTrigger::new(args.trigger_file);
+            if let Some(debounce) = args.trigger_debounce {
+                file_trigger.set_debounce(debounce.into());
+            }
+            triggers.push(Box::new(file_trigger));
+        }
         if let Some(http) = args.http {
             debug!("Setting up HttpTrigger on {http}.");
-            triggers.push(Box::new(HttpTrigger::new(http)));
+            let mut http_trigger = HttpTrigger::new(http);
+            if let Some(git_ref) = git_ref {
+                http_trigger.set_branch(git_ref);
+            }
+            http_trigger.set_tags(follows_tags);
+            http_trigger.set_paths(args.http_path);
+            http_trigger.set_methods(args.http_method);
+            http_trigger.set_sync(args.http_sync);
+            match (args.http_tls_cert, args.http_tls_key) {
+                (Some(certificate), Some(private_key)) => {
+                    http_trigger.set_tls(certificate, private_key)
+                }
+                (None, None) => {}
+                _ => return Err(MainError::IncompleteTlsArgs),
+            }
+            if let Some(secret) = args.http_secret {
+                http_trigger.set_secret(secret);
+            }
+            triggers.push(Box::new(http_trigger));
+        }
+        if let Some(windows) = windows {
+            debug!("Setting up WindowTrigger to apply held updates.");
+            triggers.push(Box::new(WindowTrigger::new(windows)));
         }
     }
 
-    // Setup check.
-    let git_trigger = args.trigger.into();
-    debug!("Setting up GitCheck for \"{directory}\" on every {git_trigger}.");
-    let mut git_check = GitCheck::open(&directory, args.git_known_host, git_trigger)?;
-    if let Some(ssh_key) = args.ssh_key {
-        git_check.set_auth(CredentialAuth::Ssh(ssh_key));
-    }
-    if let (Some(username), Some(password)) = (args.git_username, args.git_token) {
-        git_check.set_auth(CredentialAuth::Https(username, password));
-    }
-    let mut check: Box<dyn Check> = Box::new(git_check);
-
     // Setup actions.
     if arg_actions
         .iter()
@@ -100,20 +295,51 @@ fn main_inner() -> Result<(), MainError> {
     {
         return Err(MainError::MultipleProcessArgs);
     }
-    let mut actions: Vec<Box<dyn Action>> = vec![];
+    let mut grouped_actions: Vec<(ArgAction, Vec<String>)> = vec![];
     for arg_action in arg_actions {
+        match arg_action {
+            ArgAction::WhenChanged(glob) => grouped_actions
+                .last_mut()
+                .ok_or(MainError::WhenChangedWithoutAction)?
+                .1
+                .push(glob),
+            arg_action => grouped_actions.push((arg_action, vec![])),
+        }
+    }
+    // Start the actions in the current release, the updates run in the new release directory
+    let action_directory = releases
+        .as_ref()
+        .filter(|r| r.current().is_some())
+        .map_or(directory.clone(), |r| {
+            r.current_path().to_string_lossy().to_string()
+        });
+    let mut actions: Vec<Box<dyn Action>> = vec![];
+    for (arg_action, globs) in grouped_actions {
+        let (excludes, includes): (Vec<String>, Vec<String>) =
+            globs.into_iter().partition(|glob| glob.starts_with('!'));
+        let excludes: Vec<String> = excludes
+            .iter()
+            .filter_map(|glob| glob.strip_prefix('!'))
+            .map(String::from)
+            .collect();
+        let paths = PathFilter::new(&includes, &excludes)?;
         match arg_action {
             ArgAction::Script(script, runs_in_shell) => {
                 debug!("Setting up ScriptAction {script:?} on change.");
-                actions.push(Box::new(
-                    ScriptAction::new(directory.clone(), script, runs_in_shell)
-                        .map_err(ActionError::from)?,
-                ));
+                let mut script_action =
+                    ScriptAction::new(action_directory.clone(), script, runs_in_shell)
+                        .map_err(ActionError::from)?;
+                script_action.set_paths(paths);
+                actions.push(Box::new(script_action));
             }
             ArgAction::Process(process, runs_in_shell) => {
                 debug!("Setting up ProcessAction {process:?} on change.");
+                // The processes always run from the current release, even after switching
+                let process_directory = releases.as_ref().map_or(directory.clone(), |r| {
+                    r.current_path().to_string_lossy().to_string()
+                });
                 let mut process_params =
-                    ProcessParams::new(process, directory.clone(), runs_in_shell)
+                    ProcessParams::new(process, process_directory, runs_in_shell)
                         .map_err(ActionError::from)?;
 
                 if let Some(retries) = args.process_retries {
@@ -127,11 +353,18 @@ fn main_inner() -> Result<(), MainError> {
                 if let Some(stop_timeout) = args.stop_timeout {
                     process_params.set_stop_timeout(stop_timeout.into());
                 }
+                process_params.set_paths(paths);
 
-                actions.push(Box::new(
-                    ProcessAction::new(process_params).map_err(ActionError::from)?,
-                ));
+                if releases.as_ref().is_some_and(|r| r.current().is_none()) {
+                    debug!("There is no release yet, starting the process after the first one.");
+                    actions.push(Box::new(ProcessAction::new_stopped(process_params)));
+                } else {
+                    actions.push(Box::new(
+                        ProcessAction::new(process_params).map_err(ActionError::from)?,
+                    ));
+                }
             }
+            ArgAction::WhenChanged(_) => unreachable!("globs are grouped with the actions"),
         }
     }
 
@@ -139,12 +372,71 @@ fn main_inner() -> Result<(), MainError> {
         warn!("There are no actions defined: we will only pull!");
     }
 
+    // Setup rollback.
+    if releases.is_some() && (args.rollback || args.rollback_script.is_some()) {
+        warn!("The current release is kept if an action fails, ignoring the rollback.");
+    }
+    let rollback = match args.rollback_script {
+        _ if releases.is_some() => RollbackPolicy::Disabled,
+        Some(script) => {
+            debug!("Setting up ScriptAction {script:?} on rollback.");
+            let script_action =
+                ScriptAction::new(directory.clone(), script, true).map_err(ActionError::from)?;
+            RollbackPolicy::Actions(vec![Box::new(script_action)])
+        }
+        None if args.rollback => {
+            debug!("Setting up rollback, rerunning the actions.");
+            RollbackPolicy::Rerun
+        }
+        None => RollbackPolicy::Disabled,
+    };
+
+    // Setup history.
+    let history = match args.state_dir {
+        Some(state_dir) => {
+            debug!("Saving the history to \"{state_dir}\".");
+            Some(History::open(state_dir)?)
+        }
+        None => None,
+    };
+
     // Start the main script.
-    start(triggers, &mut check, &mut actions)?;
+    let options = StartOptions {
+        overlap: args.overlap,
+        rollback,
+        history,
+        releases,
+    };
+    start_with_options(triggers, &mut check, &mut actions, options)?;
+    Ok(())
+}
+
+fn history_inner() -> Result<(), MainError> {
+    let args = parse_history_args();
+
+    let state_dir = args.state_dir.ok_or(MainError::MissingStateDirArg)?;
+    let history = History::new(state_dir);
+    for record in history.read(args.limit)? {
+        if args.json {
+            println!("{record}");
+        } else {
+            println!("{}", format_record(&record));
+        }
+    }
+
     Ok(())
 }
 
 fn main() {
+    if is_history_command() {
+        // The logger is not set up for the subcommand, so print the errors directly
+        if let Err(err) = history_inner() {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+        return;
+    }
+
     if let Err(err) = main_inner() {
         error!("{err}");
         process::exit(1);
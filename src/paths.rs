@@ -0,0 +1,227 @@
+use crate::context::Context;
+use glob::{MatchOptions, Pattern};
+use thiserror::Error;
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// A custom error for describing the error cases for path filters.
+#[derive(Debug, Error)]
+pub enum PathFilterError {
+    /// The pattern is not a valid glob.
+    #[error("path pattern {0} is invalid: {1}")]
+    InvalidPattern(String, String),
+}
+
+/// A glob matching a path in the repository.
+///
+/// If the glob doesn't contain a `/`, it is matched against every file and directory name
+/// (e.g. `*.md`), otherwise against the path from the root (e.g. `services/api`).
+/// If a directory matches, every file under it matches as well.
+#[derive(Debug, Clone)]
+struct PathPattern {
+    pattern: Pattern,
+    anchored: bool,
+}
+
+impl PathPattern {
+    fn new(pattern: &str) -> Result<Self, PathFilterError> {
+        let trimmed = pattern.trim().trim_end_matches('/');
+        let anchored = trimmed.contains('/');
+        let glob = Pattern::new(trimmed.trim_start_matches('/'))
+            .map_err(|err| PathFilterError::InvalidPattern(pattern.to_string(), err.to_string()))?;
+
+        Ok(Self {
+            pattern: glob,
+            anchored,
+        })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        // Check the path and all of the parent directories
+        path.match_indices('/')
+            .map(|(i, _)| &path[..i])
+            .chain([path])
+            .any(|prefix| {
+                if self.anchored {
+                    self.pattern.matches_with(prefix, MATCH_OPTIONS)
+                } else {
+                    let name = prefix.rsplit('/').next().unwrap_or(prefix);
+                    self.pattern.matches_with(name, MATCH_OPTIONS)
+                }
+            })
+    }
+}
+
+/// Include and exclude globs to decide which changed files are relevant.
+///
+/// A path is relevant if it matches any of the includes (or there are no includes),
+/// and it doesn't match any of the excludes.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    includes: Vec<PathPattern>,
+    excludes: Vec<PathPattern>,
+}
+
+impl PathFilter {
+    /// Create a filter from the include and exclude globs.
+    pub fn new(includes: &[String], excludes: &[String]) -> Result<Self, PathFilterError> {
+        Ok(Self {
+            includes: includes
+                .iter()
+                .map(|p| PathPattern::new(p))
+                .collect::<Result<_, _>>()?,
+            excludes: excludes
+                .iter()
+                .map(|p| PathPattern::new(p))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Returns true if there are no globs, so every path matches.
+    pub fn is_empty(&self) -> bool {
+        self.includes.is_empty() && self.excludes.is_empty()
+    }
+
+    /// Check if the path is relevant.
+    pub fn matches(&self, path: &str) -> bool {
+        (self.includes.is_empty() || self.includes.iter().any(|p| p.matches(path)))
+            && !self.excludes.iter().any(|p| p.matches(path))
+    }
+
+    /// Return the relevant paths from the list.
+    pub fn filter<'a>(&self, paths: &'a [String]) -> Vec<&'a String> {
+        paths.iter().filter(|path| self.matches(path)).collect()
+    }
+
+    /// Check if any of the changed files in the context is relevant.
+    ///
+    /// If the check doesn't report the changed files, we cannot decide, so every change is relevant.
+    pub fn matches_changes(&self, context: &Context) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        match context
+            .get("GIT_CHANGED_FILES")
+            .or_else(|| context.get("WATCH_CHANGED_FILES"))
+        {
+            Some(changed_files) => changed_files.lines().any(|path| self.matches(path)),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn create_filter(includes: &[&str], excludes: &[&str]) -> PathFilter {
+        let includes: Vec<String> = includes.iter().map(|s| s.to_string()).collect();
+        let excludes: Vec<String> = excludes.iter().map(|s| s.to_string()).collect();
+        PathFilter::new(&includes, &excludes).unwrap()
+    }
+
+    #[test]
+    fn it_should_match_everything_without_globs() {
+        let filter = create_filter(&[], &[]);
+
+        assert!(filter.is_empty());
+        assert!(filter.matches("README.md"));
+        assert!(filter.matches("services/api/src/main.rs"));
+    }
+
+    #[test]
+    fn it_should_match_directories_from_the_root() {
+        let filter = create_filter(&["services/api/"], &[]);
+
+        assert!(filter.matches("services/api/src/main.rs"));
+        assert!(filter.matches("services/api/Dockerfile"));
+        assert!(!filter.matches("services/web/src/main.rs"));
+        assert!(!filter.matches("other/services/api/main.rs"));
+
+        // A leading slash anchors the name to the root
+        let filter = create_filter(&["/docs"], &[]);
+        assert!(filter.matches("docs/index.md"));
+        assert!(!filter.matches("services/docs/index.md"));
+    }
+
+    #[test]
+    fn it_should_match_globs() {
+        let filter = create_filter(&["services/*/src"], &[]);
+        assert!(filter.matches("services/api/src/main.rs"));
+        assert!(!filter.matches("services/api/tests/main.rs"));
+
+        let filter = create_filter(&["services/**/*.rs"], &[]);
+        assert!(filter.matches("services/api/src/main.rs"));
+        assert!(!filter.matches("services/api/package.json"));
+    }
+
+    #[test]
+    fn it_should_match_names_everywhere() {
+        let filter = create_filter(&["*.md"], &[]);
+
+        assert!(filter.matches("README.md"));
+        assert!(filter.matches("docs/content/index.md"));
+        assert!(!filter.matches("src/main.rs"));
+    }
+
+    #[test]
+    fn it_should_exclude_paths() {
+        let filter = create_filter(&["services/api"], &["*.md", "services/api/tests"]);
+
+        assert!(filter.matches("services/api/src/main.rs"));
+        assert!(!filter.matches("services/api/README.md"));
+        assert!(!filter.matches("services/api/tests/main.rs"));
+
+        let filter = create_filter(&[], &["docs"]);
+        assert!(filter.matches("src/main.rs"));
+        assert!(!filter.matches("docs/index.md"));
+    }
+
+    #[test]
+    fn it_should_filter_the_paths() {
+        let filter = create_filter(&["frontend"], &[]);
+        let paths = vec![
+            String::from("frontend/index.html"),
+            String::from("backend/main.rs"),
+            String::from("frontend/style.css"),
+        ];
+
+        assert_eq!(
+            vec!["frontend/index.html", "frontend/style.css"],
+            filter.filter(&paths)
+        );
+    }
+
+    #[test]
+    fn it_should_match_the_changes_in_the_context() {
+        let filter = create_filter(&["frontend"], &[]);
+
+        let context: Context = HashMap::from([(
+            "GIT_CHANGED_FILES",
+            String::from("backend/main.rs\nfrontend/index.html"),
+        )]);
+        assert!(filter.matches_changes(&context));
+
+        let context: Context =
+            HashMap::from([("WATCH_CHANGED_FILES", String::from("backend/main.rs"))]);
+        assert!(!filter.matches_changes(&context));
+
+        // Without changed files every change is relevant
+        assert!(filter.matches_changes(&HashMap::new()));
+    }
+
+    #[test]
+    fn it_should_fail_on_invalid_glob() {
+        let result = PathFilter::new(&[String::from("src/[")], &[]);
+
+        assert!(
+            matches!(result, Err(PathFilterError::InvalidPattern(_, _))),
+            "{result:?} should be InvalidPattern"
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{
+    checks::{Check, CheckError},
+    context::Context,
+};
+use log::{debug, info, warn};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+use thiserror::Error;
+
+/// The name of the directory, where the releases are checked out.
+pub const RELEASES_DIRECTORY: &str = "releases";
+
+/// The name of the symlink, that points to the active release.
+pub const CURRENT_LINK: &str = "current";
+
+/// The name of the file, where the releases are listed in the order they were created.
+pub const RELEASES_ORDER: &str = ".releases";
+
+/// The number of releases to keep by default, including the current one.
+pub const DEFAULT_KEEP_RELEASES: usize = 5;
+
+/// A custom error for creating and switching releases.
+#[derive(Debug, Error)]
+pub enum ReleaseError {
+    #[error("the check cannot create releases")]
+    NotSupported,
+    #[error("there is no commit to release")]
+    MissingCommit,
+    #[error("release {0} is the current release, it cannot be replaced")]
+    AlreadyCurrent(String),
+    #[error("cannot create release directory {0}: {1}")]
+    CreateFailed(String, io::Error),
+    #[error("cannot check out the release: {0}")]
+    ExportFailed(#[from] CheckError),
+    #[error("cannot switch {0} to the new release: {1}")]
+    SwitchFailed(String, io::Error),
+}
+
+/// Release directories, where every update is checked out separately.
+///
+/// The updates are checked out to `releases/<sha>`, and the `current` symlink
+/// is only switched to it, after the actions succeeded. The live application
+/// (running from `current`) never sees a half-updated tree.
+#[derive(Debug, Clone)]
+pub struct Releases {
+    directory: PathBuf,
+    keep: usize,
+}
+
+impl Releases {
+    /// Create the releases in the directory, the releases and the symlink are created on the first update.
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        Releases {
+            directory: directory.as_ref().to_path_buf(),
+            keep: DEFAULT_KEEP_RELEASES,
+        }
+    }
+
+    /// Keep this many releases (including the current one), remove the older ones.
+    pub fn set_keep(&mut self, keep: usize) {
+        self.keep = keep.max(1);
+    }
+
+    /// The path of the symlink, that points to the active release.
+    pub fn current_path(&self) -> PathBuf {
+        self.directory.join(CURRENT_LINK)
+    }
+
+    /// The path of the release directory with this name.
+    pub fn release_path(&self, name: &str) -> PathBuf {
+        self.directory.join(RELEASES_DIRECTORY).join(name)
+    }
+
+    /// The name of the active release, if there is any.
+    pub fn current(&self) -> Option<String> {
+        let target = fs::read_link(self.current_path()).ok()?;
+        target
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    }
+
+    /// Check out the updated commit to a new release directory, and point the actions to it.
+    pub fn create(
+        &self,
+        check: &mut Box<dyn Check>,
+        context: &mut Context,
+    ) -> Result<(), ReleaseError> {
+        let name = context
+            .get("GIT_COMMIT_SHA")
+            .cloned()
+            .ok_or(ReleaseError::MissingCommit)?;
+        if self.current().as_ref() == Some(&name) {
+            return Err(ReleaseError::AlreadyCurrent(name));
+        }
+
+        // Start from an empty directory, if an earlier release of the commit failed
+        let path = self.release_path(&name);
+        let create_failed = |err| ReleaseError::CreateFailed(path.display().to_string(), err);
+        if path.exists() {
+            debug!("Removing the earlier release {name}.");
+            fs::remove_dir_all(&path).map_err(create_failed)?;
+        }
+        fs::create_dir_all(&path).map_err(create_failed)?;
+
+        if !check.export(context, &path)? {
+            let _ = fs::remove_dir_all(&path);
+            return Err(ReleaseError::NotSupported);
+        }
+
+        // Record the order of the releases, to know which ones are the oldest
+        let mut order = self.read_order();
+        order.retain(|release| release != &name);
+        order.push(name.clone());
+        self.write_order(&order).map_err(|err| {
+            ReleaseError::CreateFailed(self.order_path().display().to_string(), err)
+        })?;
+
+        info!("Created release {name} in {}.", path.display());
+        context.insert("RELEASE_NAME", name);
+        context.insert("RELEASE_DIRECTORY", path.to_string_lossy().to_string());
+
+        Ok(())
+    }
+
+    /// Switch the current symlink to the created release.
+    ///
+    /// Returns false if there was no release created.
+    pub fn activate(&self, context: &Context) -> Result<bool, ReleaseError> {
+        let Some(name) = context.get("RELEASE_NAME") else {
+            return Ok(false);
+        };
+
+        self.switch(name).map_err(|err| {
+            ReleaseError::SwitchFailed(self.current_path().display().to_string(), err)
+        })?;
+        info!("Switched to release {name}.");
+
+        Ok(true)
+    }
+
+    /// Remove the created release, because the actions failed.
+    pub fn discard(&self, context: &Context) {
+        let Some(name) = context.get("RELEASE_NAME") else {
+            return;
+        };
+        if self.current().as_ref() == Some(name) {
+            return;
+        }
+
+        let path = self.release_path(name);
+        match fs::remove_dir_all(&path) {
+            Ok(()) => info!("Removed the failed release {name}."),
+            Err(err) => warn!(
+                "Failed removing the failed release {}: {err}.",
+                path.display()
+            ),
+        }
+
+        let mut order = self.read_order();
+        order.retain(|release| release != name);
+        if let Err(err) = self.write_order(&order) {
+            warn!("Failed saving the order of the releases: {err}.");
+        }
+    }
+
+    fn order_path(&self) -> PathBuf {
+        self.directory.join(RELEASES_ORDER)
+    }
+
+    /// Read the names of the releases, from the oldest to the newest.
+    fn read_order(&self) -> Vec<String> {
+        fs::read_to_string(self.order_path())
+            .map(|order| order.lines().map(String::from).collect())
+            .unwrap_or_default()
+    }
+
+    fn write_order(&self, order: &[String]) -> io::Result<()> {
+        let content: String = order.iter().map(|name| format!("{name}\n")).collect();
+        fs::write(self.order_path(), content)
+    }
+
+    /// Point the current symlink to the release, by renaming a new symlink over it.
+    ///
+    /// The rename is atomic on *NIX, on Windows the old symlink has to be removed first.
+    fn switch(&self, name: &str) -> io::Result<()> {
+        let target = Path::new(RELEASES_DIRECTORY).join(name);
+        let temporary = self
+            .directory
+            .join(format!("{CURRENT_LINK}.{}", process::id()));
+        let _ = fs::remove_file(&temporary);
+
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&target, &temporary)?;
+
+        #[cfg(windows)]
+        {
+            std::os::windows::fs::symlink_dir(self.directory.join(&target), &temporary)?;
+            if self.current().is_some() {
+                fs::remove_dir(self.current_path())?;
+            }
+        }
+
+        fs::rename(&temporary, self.current_path())
+    }
+
+    /// Remove the oldest releases, keeping the current one, the newest ones and the ones in use
+    /// (e.g. the directory of a running process).
+    pub fn prune(&self, in_use: &[PathBuf]) {
+        let Ok(entries) = fs::read_dir(self.directory.join(RELEASES_DIRECTORY)) else {
+            return;
+        };
+        let existing: Vec<String> = entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+
+        // The releases missing from the order (e.g. created by hand) are the oldest
+        let order = self.read_order();
+        let mut releases: Vec<String> = existing
+            .iter()
+            .filter(|name| !order.contains(name))
+            .cloned()
+            .collect();
+        releases.sort();
+        releases.extend(order.into_iter().filter(|name| existing.contains(name)));
+
+        let current = self.current();
+        let mut kept = usize::from(current.is_some());
+        let mut removed = vec![];
+        for name in releases.iter().rev() {
+            if Some(name) == current.as_ref() {
+                continue;
+            }
+            if kept < self.keep {
+                kept += 1;
+                continue;
+            }
+
+            let path = self.release_path(name);
+            if fs::canonicalize(&path).is_ok_and(|path| in_use.contains(&path)) {
+                debug!("Keeping the old release {name}, because it is still in use.");
+                continue;
+            }
+            match fs::remove_dir_all(&path) {
+                Ok(()) => {
+                    debug!("Removed the old release {}.", path.display());
+                    removed.push(name.clone());
+                }
+                Err(err) => warn!("Failed removing the old release {}: {err}.", path.display()),
+            }
+        }
+
+        releases.retain(|name| !removed.contains(name));
+        if let Err(err) = self.write_order(&releases) {
+            warn!("Failed saving the order of the releases: {err}.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::MockCheck;
+    use rand::distr::{Alphanumeric, SampleString};
+    use std::{collections::HashMap, error::Error};
+
+    fn create_check() -> Box<dyn Check> {
+        let mut mock_check = MockCheck::new();
+        mock_check.expect_export().returning(|context, directory| {
+            fs::write(directory.join("version"), &context["GIT_COMMIT_SHA"]).unwrap();
+            Ok(true)
+        });
+        Box::new(mock_check)
+    }
+
+    fn release(releases: &Releases, check: &mut Box<dyn Check>, sha: &str, in_use: &[PathBuf]) {
+        let mut context: Context = HashMap::from([("GIT_COMMIT_SHA", sha.to_string())]);
+        releases.create(check, &mut context).unwrap();
+        releases.activate(&context).unwrap();
+        releases.prune(in_use);
+    }
+
+    #[test]
+    fn it_should_switch_to_the_new_release() -> Result<(), Box<dyn Error>> {
+        let id = Alphanumeric.sample_string(&mut rand::rng(), 16);
+        let directory = format!("test_directories/{id}");
+        let releases = Releases::new(&directory);
+        let mut check = create_check();
+
+        let mut context: Context = HashMap::from([("GIT_COMMIT_SHA", String::from("abc123"))]);
+        releases.create(&mut check, &mut context)?;
+        assert_eq!(
+            format!("{directory}/releases/abc123"),
+            context["RELEASE_DIRECTORY"]
+        );
+        assert_eq!(None, releases.current());
+
+        assert!(releases.activate(&context)?);
+        assert_eq!(Some(String::from("abc123")), releases.current());
+        assert_eq!(
+            "abc123",
+            fs::read_to_string(format!("{directory}/current/version"))?
+        );
+
+        let mut context: Context = HashMap::from([("GIT_COMMIT_SHA", String::from("abc123"))]);
+        let result = releases.create(&mut check, &mut context);
+        assert!(
+            matches!(result, Err(ReleaseError::AlreadyCurrent(_))),
+            "{result:?} should be AlreadyCurrent"
+        );
+
+        fs::remove_dir_all(&directory)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_prune_the_old_releases() -> Result<(), Box<dyn Error>> {
+        let id = Alphanumeric.sample_string(&mut rand::rng(), 16);
+        let directory = format!("test_directories/{id}");
+        let mut releases = Releases::new(&directory);
+        releases.set_keep(2);
+        let mut check = create_check();
+
+        release(&releases, &mut check, "abc123", &[]);
+        release(&releases, &mut check, "def456", &[]);
+
+        // The oldest release should be pruned, even if it was modified later
+        fs::write(format!("{directory}/releases/abc123/log"), "log")?;
+        release(&releases, &mut check, "ghi789", &[]);
+
+        assert!(!Path::new(&format!("{directory}/releases/abc123")).exists());
+        assert!(Path::new(&format!("{directory}/releases/def456")).exists());
+        assert_eq!(Some(String::from("ghi789")), releases.current());
+
+        // The releases in use should be kept
+        let in_use = fs::canonicalize(format!("{directory}/releases/def456"))?;
+        release(&releases, &mut check, "jkl012", &[in_use]);
+        assert!(Path::new(&format!("{directory}/releases/def456")).exists());
+        assert!(Path::new(&format!("{directory}/releases/ghi789")).exists());
+
+        release(&releases, &mut check, "mno345", &[]);
+        assert!(!Path::new(&format!("{directory}/releases/def456")).exists());
+        assert!(!Path::new(&format!("{directory}/releases/ghi789")).exists());
+        assert_eq!(
+            "jkl012\nmno345\n",
+            fs::read_to_string(format!("{directory}/{RELEASES_ORDER}"))?
+        );
+
+        fs::remove_dir_all(&directory)?;
+
+        Ok(())
+    }
+}
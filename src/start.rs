@@ -2,10 +2,21 @@ use crate::{
     actions::Action,
     checks::{Check, CheckError},
     context::Context,
-    triggers::{Trigger, TriggerError},
+    history::{History, RunRecord},
+    releases::{ReleaseError, Releases},
+    triggers::{Trigger, TriggerError, TriggerMessage},
+};
+use chrono::Utc;
+use log::{debug, error, info, warn};
+use serde_json::{json, Value};
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    str::FromStr,
+    sync::mpsc::{self, Sender},
+    thread,
+    time::{Duration, Instant},
 };
-use log::{debug, error, info};
-use std::{sync::mpsc, thread};
 use thiserror::Error;
 
 /// A custom error implementation for the start function
@@ -19,13 +30,357 @@ pub enum StartError {
     FailedCheck(#[from] CheckError),
 }
 
+/// What to do with the triggers that arrive while the check and the actions are running.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// Run a check for every trigger, one after another.
+    Queue,
+    /// Collapse the triggers into a single check, with the contexts merged (the latest value wins).
+    #[default]
+    Coalesce,
+    /// Ignore the triggers, that arrived while running.
+    Drop,
+}
+
+impl FromStr for OverlapPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "queue" => Ok(OverlapPolicy::Queue),
+            "coalesce" => Ok(OverlapPolicy::Coalesce),
+            "drop" => Ok(OverlapPolicy::Drop),
+            s => Err(format!(
+                "cannot parse {s}, valid values: queue, coalesce, drop"
+            )),
+        }
+    }
+}
+
+/// What to do if an action fails after an update.
+#[derive(Default)]
+pub enum RollbackPolicy {
+    /// Stay on the new version.
+    #[default]
+    Disabled,
+    /// Roll back the update and run the actions again, to restore the previous version.
+    Rerun,
+    /// Roll back the update and run these actions instead (e.g. a rollback script).
+    Actions(Vec<Box<dyn Action>>),
+}
+
+/// The options of the main program loop.
+#[derive(Default)]
+pub struct StartOptions {
+    /// What to do with the triggers that arrive while running.
+    pub overlap: OverlapPolicy,
+    /// What to do if an action fails after an update.
+    pub rollback: RollbackPolicy,
+    /// Where to save the runs with updates, if at all.
+    pub history: Option<History>,
+    /// Check out the updates to release directories, instead of running the actions in place.
+    ///
+    /// The current release is kept if an action fails, so nothing is rolled back.
+    pub releases: Option<Releases>,
+}
+
+/// A trigger waiting to be run, which can be the merge of multiple triggers.
+struct PendingRun {
+    context: Context,
+    replies: Vec<Sender<RunReport>>,
+}
+
+impl From<TriggerMessage> for PendingRun {
+    fn from(message: TriggerMessage) -> Self {
+        Self {
+            context: message.context,
+            replies: message.reply.into_iter().collect(),
+        }
+    }
+}
+
+impl PendingRun {
+    fn merge(&mut self, message: TriggerMessage) {
+        self.context.extend(message.context);
+        self.replies.extend(message.reply);
+    }
+}
+
+/// The result of an action in a run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActionReport {
+    pub name: String,
+    pub skipped: bool,
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+    pub error: Option<String>,
+}
+
+impl ActionReport {
+    /// Convert the report to a JSON value.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "success": self.error.is_none(),
+            "skipped": self.skipped,
+            "exit_code": self.exit_code,
+            "duration_ms": self.duration.as_millis() as u64,
+            "error": self.error,
+        })
+    }
+}
+
+/// The result of running the check and the actions for a trigger.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunReport {
+    pub updated: bool,
+    pub commit_sha: Option<String>,
+    pub held_commit_sha: Option<String>,
+    pub actions: Vec<ActionReport>,
+    pub rolled_back: bool,
+    pub error: Option<String>,
+}
+
+impl RunReport {
+    /// Returns true if the check and every action ran successfully.
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && self.actions.iter().all(|a| a.error.is_none())
+    }
+
+    /// Convert the report to a JSON string.
+    pub fn to_json(&self) -> String {
+        json!({
+            "success": self.is_success(),
+            "updated": self.updated,
+            "commit_sha": self.commit_sha,
+            "held_commit_sha": self.held_commit_sha,
+            "actions": self
+                .actions
+                .iter()
+                .map(ActionReport::to_json)
+                .collect::<Vec<_>>(),
+            "rolled_back": self.rolled_back,
+            "error": self.error,
+        })
+        .to_string()
+    }
+}
+
+/// Roll back the failed update, and run the actions to restore the previous version.
+///
+/// Returns true if the previous version is restored.
+fn roll_back(
+    check: &mut Box<dyn Check>,
+    actions: &mut [Box<dyn Action>],
+    rollback: &mut RollbackPolicy,
+    context: &mut Context,
+) -> bool {
+    let rollback_actions = match rollback {
+        RollbackPolicy::Disabled => return false,
+        RollbackPolicy::Rerun => actions,
+        RollbackPolicy::Actions(rollback_actions) => rollback_actions.as_mut_slice(),
+    };
+    match check.rollback(context) {
+        Ok(true) => {}
+        Ok(false) => {
+            warn!("The update cannot be rolled back.");
+            return false;
+        }
+        Err(err) => {
+            error!("Rollback failed: {err}.");
+            return false;
+        }
+    }
+
+    info!("Rolled back the update, running actions for the previous version.");
+    for action in rollback_actions.iter_mut() {
+        if !action.is_relevant(context) {
+            continue;
+        }
+        if let Err(err) = action.run(context) {
+            error!("Action failed while rolling back: {err}.");
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Run the action if it is relevant, and add it to the report. Returns false if it failed.
+fn run_action(action: &mut Box<dyn Action>, context: &Context, report: &mut RunReport) -> bool {
+    if !action.is_relevant(context) {
+        info!(
+            "Skipping action {:?}, none of the changed files match.",
+            action.name()
+        );
+        report.actions.push(ActionReport {
+            name: action.name(),
+            skipped: true,
+            ..Default::default()
+        });
+        return true;
+    }
+    let started = Instant::now();
+    let result = action.run(context);
+    let duration = started.elapsed();
+    let exit_code = match &result {
+        Ok(()) => Some(0),
+        Err(err) => err.exit_code(),
+    };
+    let error = result.err().map(|err| err.to_string());
+    let failed = error.is_some();
+    if let Some(err) = &error {
+        error!("Action failed, we will not continue: {err}.");
+    }
+    report.actions.push(ActionReport {
+        name: action.name(),
+        skipped: false,
+        exit_code,
+        duration,
+        error,
+    });
+
+    !failed
+}
+
+/// Run the check and if there are updates, the actions, then report the results.
+///
+/// With releases, the updates that were pulled but could not be released are saved to
+/// `unreleased`, and they are released again on the next run.
+fn run(
+    check: &mut Box<dyn Check>,
+    actions: &mut [Box<dyn Action>],
+    rollback: &mut RollbackPolicy,
+    releases: Option<&Releases>,
+    unreleased: &mut Option<Context>,
+    context: &mut Context,
+) -> RunReport {
+    let mut report = RunReport::default();
+    let trigger_context = context.clone();
+    match check.check(context) {
+        Ok(true) => *unreleased = None,
+        Ok(false) => {
+            let Some(unreleased_context) = unreleased.take() else {
+                debug!("There are no updates.");
+                report.held_commit_sha = context.get("GIT_HELD_COMMIT_SHA").cloned();
+                return report;
+            };
+            // The working tree is already updated, so only the release has to be created again
+            *context = unreleased_context;
+            context.extend(trigger_context);
+            info!("Retrying the release, that failed before.");
+        }
+        Err(err) => {
+            error!("Check failed: {err}.");
+            report.error = Some(err.to_string());
+            return report;
+        }
+    }
+
+    info!(
+        "There are updates, {}.",
+        if actions.is_empty() {
+            "pulling"
+        } else {
+            "running actions"
+        }
+    );
+    report.updated = true;
+    report.commit_sha = context.get("GIT_COMMIT_SHA").cloned();
+    let Some(releases) = releases else {
+        for action in actions.iter_mut() {
+            if !run_action(action, context, &mut report) {
+                report.rolled_back = roll_back(check, actions, rollback, context);
+                break;
+            }
+        }
+        return report;
+    };
+
+    let checked_context = context.clone();
+    if let Err(err) = releases.create(check, context) {
+        error!("Creating the release failed: {err}.");
+        report.error = Some(err.to_string());
+        if !matches!(err, ReleaseError::AlreadyCurrent(_)) {
+            *unreleased = Some(checked_context);
+        }
+        return report;
+    }
+
+    // The actions running from the release (e.g. processes) are only restarted after
+    // switching to it, the others run before in the new release directory
+    for action in actions
+        .iter_mut()
+        .filter(|action| !action.runs_after_activation())
+    {
+        if !run_action(action, context, &mut report) {
+            releases.discard(context);
+            *unreleased = Some(checked_context);
+            return report;
+        }
+    }
+    if let Err(err) = releases.activate(context) {
+        error!("Switching to the release failed: {err}.");
+        report.error = Some(err.to_string());
+        *unreleased = Some(checked_context);
+        return report;
+    }
+    for action in actions
+        .iter_mut()
+        .filter(|action| action.runs_after_activation())
+    {
+        if !run_action(action, context, &mut report) {
+            break;
+        }
+    }
+
+    // Keep the releases, where the processes are still running
+    let in_use: Vec<PathBuf> = actions
+        .iter()
+        .filter_map(|action| action.directory_in_use())
+        .collect();
+    releases.prune(&in_use);
+
+    report
+}
+
 /// The main program loop, that runs the triggers, checks and actions infinitely.
 pub fn start(
     triggers: Vec<Box<dyn Trigger>>,
     check: &mut Box<dyn Check>,
     actions: &mut [Box<dyn Action>],
 ) -> Result<(), StartError> {
-    let (tx, rx) = mpsc::channel::<Option<Context>>();
+    start_with_overlap(triggers, check, actions, OverlapPolicy::default())
+}
+
+/// The main program loop, with a policy for the triggers that arrive while running.
+pub fn start_with_overlap(
+    triggers: Vec<Box<dyn Trigger>>,
+    check: &mut Box<dyn Check>,
+    actions: &mut [Box<dyn Action>],
+    overlap: OverlapPolicy,
+) -> Result<(), StartError> {
+    let options = StartOptions {
+        overlap,
+        ..Default::default()
+    };
+    start_with_options(triggers, check, actions, options)
+}
+
+/// The main program loop, with the options for overlapping triggers, rollbacks, history and releases.
+pub fn start_with_options(
+    triggers: Vec<Box<dyn Trigger>>,
+    check: &mut Box<dyn Check>,
+    actions: &mut [Box<dyn Action>],
+    options: StartOptions,
+) -> Result<(), StartError> {
+    let StartOptions {
+        overlap,
+        mut rollback,
+        history,
+        releases,
+    } = options;
+    let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
 
     if triggers.is_empty() {
         return Err(StartError::NoTriggers);
@@ -42,30 +397,106 @@ pub fn start(
     }
 
     debug!("Waiting on triggers.");
-    while let Ok(Some(mut context)) = rx.recv() {
-        match check.check(&mut context) {
-            Ok(true) => {
-                info!(
-                    "There are updates, {}.",
-                    if actions.is_empty() {
-                        "pulling"
-                    } else {
-                        "running actions"
-                    }
+    let mut pending: VecDeque<PendingRun> = VecDeque::new();
+    let mut terminating = false;
+    let mut unreleased: Option<Context> = None;
+    loop {
+        // Stop taking work on termination, only answer the waiting triggers
+        if terminating {
+            if !pending.is_empty() {
+                debug!("Terminating, skipping {} pending triggers.", pending.len());
+            }
+            let report = RunReport {
+                error: Some(String::from("gw is terminating, the trigger was not run")),
+                ..Default::default()
+            };
+            for reply in pending
+                .drain(..)
+                .flat_map(|pending_run| pending_run.replies)
+            {
+                if reply.send(report.clone()).is_err() {
+                    debug!("Failed to report the result, the trigger has hang up.");
+                }
+            }
+            break;
+        }
+
+        let PendingRun {
+            mut context,
+            replies,
+        } = if let Some(pending_run) = pending.pop_front() {
+            pending_run
+        } else {
+            match rx.recv() {
+                Ok(Some(message)) => message.into(),
+                _ => break,
+            }
+        };
+
+        let started_at = Utc::now();
+        let report = run(
+            check,
+            actions,
+            &mut rollback,
+            releases.as_ref(),
+            &mut unreleased,
+            &mut context,
+        );
+        // Save the updates and the failures, but not the checks without updates
+        if let (Some(history), true) = (&history, report.updated || !report.is_success()) {
+            let record = RunRecord::new(&context, report.clone(), started_at, Utc::now());
+            if let Err(err) = history.append(&record) {
+                warn!("Failed to save the run to the history: {err}.");
+            }
+        }
+        for reply in replies {
+            if reply.send(report.clone()).is_err() {
+                debug!("Failed to report the result, the trigger has hang up.");
+            }
+        }
+
+        // Collect the triggers that arrived while running, until the termination
+        let mut arrived = vec![];
+        while let Ok(message) = rx.try_recv() {
+            match message {
+                Some(message) => arrived.push(message),
+                None => {
+                    terminating = true;
+                    break;
+                }
+            }
+        }
+        if arrived.is_empty() {
+            continue;
+        }
+
+        match overlap {
+            OverlapPolicy::Queue => {
+                debug!(
+                    "{} triggers arrived while running, queueing them.",
+                    arrived.len()
                 );
-                for action in actions.iter_mut() {
-                    let result = action.run(&context);
-                    if let Err(err) = result {
-                        error!("Action failed, we will not continue: {err}.");
-                        break;
+                pending.extend(arrived.into_iter().map(PendingRun::from));
+            }
+            OverlapPolicy::Coalesce => {
+                debug!(
+                    "{} triggers arrived while running, running once.",
+                    arrived.len()
+                );
+                let mut arrived = arrived.into_iter();
+                if let Some(first) = arrived.next() {
+                    let mut pending_run = PendingRun::from(first);
+                    for message in arrived {
+                        pending_run.merge(message);
                     }
+                    pending.push_back(pending_run);
                 }
             }
-            Ok(false) => {
-                debug!("There are no updates.");
-            }
-            Err(err) => {
-                error!("Check failed: {err}.");
+            OverlapPolicy::Drop => {
+                debug!(
+                    "{} triggers arrived while running, dropping them.",
+                    arrived.len()
+                );
             }
         }
     }
@@ -79,18 +510,175 @@ pub fn start(
 mod tests {
     use super::*;
     use crate::{
-        actions::{Action, MockAction},
+        actions::{Action, ActionError, MockAction},
         checks::{Check, MockCheck},
         triggers::{MockTrigger, Trigger},
     };
-    use std::collections::HashMap;
+    use rand::distr::{Alphanumeric, SampleString};
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        thread::sleep,
+        time::Duration,
+    };
+
+    /// Zero out the measured durations, so reports can be compared.
+    fn without_durations(actions: Vec<ActionReport>) -> Vec<ActionReport> {
+        actions
+            .into_iter()
+            .map(|action| ActionReport {
+                duration: Duration::ZERO,
+                ..action
+            })
+            .collect()
+    }
+
+    /// Run with a trigger, that fires three more times while the first check is running.
+    fn run_overlapping_triggers(overlap: OverlapPolicy) -> Vec<Context> {
+        // Setup mock triggers.
+        let mut mock_trigger = MockTrigger::new();
+        mock_trigger.expect_listen().returning(|tx| {
+            tx.send(Some(
+                HashMap::from([("TRIGGER_NAME", String::from("1"))]).into(),
+            ))?;
+            sleep(Duration::from_millis(20));
+            for i in 2..=4 {
+                let mut context: Context = HashMap::from([("TRIGGER_NAME", i.to_string())]);
+                if i == 2 {
+                    context.insert("HTTP_METHOD", String::from("POST"));
+                }
+                tx.send(Some(context.into()))?;
+            }
+            // Wait for the overlapping runs, before terminating
+            sleep(Duration::from_millis(500));
+            tx.send(None)?;
+            Ok(())
+        });
+        let triggers: Vec<Box<dyn Trigger>> = vec![Box::new(mock_trigger)];
+
+        // Setup mock check, that takes some time.
+        let contexts = Arc::new(Mutex::new(vec![]));
+        let check_contexts = contexts.clone();
+        let mut mock_check = MockCheck::new();
+        mock_check.expect_check().returning(move |context| {
+            check_contexts.lock().unwrap().push(context.clone());
+            sleep(Duration::from_millis(100));
+            Ok(false)
+        });
+        let mut check: Box<dyn Check> = Box::new(mock_check);
+
+        let result = start_with_overlap(triggers, &mut check, &mut [], overlap);
+        assert!(result.is_ok());
+
+        let contexts = contexts.lock().unwrap().clone();
+        contexts
+    }
+
+    #[test]
+    fn it_should_queue_overlapping_triggers() {
+        let contexts = run_overlapping_triggers(OverlapPolicy::Queue);
+
+        let trigger_names: Vec<_> = contexts.iter().map(|c| c["TRIGGER_NAME"].clone()).collect();
+        assert_eq!(vec!["1", "2", "3", "4"], trigger_names);
+    }
+
+    #[test]
+    fn it_should_coalesce_overlapping_triggers() {
+        let contexts = run_overlapping_triggers(OverlapPolicy::Coalesce);
+
+        // The later triggers should be merged into one, with the latest value
+        assert_eq!(2, contexts.len());
+        assert_eq!("1", contexts[0]["TRIGGER_NAME"]);
+        assert_eq!("4", contexts[1]["TRIGGER_NAME"]);
+        assert_eq!("POST", contexts[1]["HTTP_METHOD"]);
+    }
+
+    #[test]
+    fn it_should_drop_overlapping_triggers() {
+        let contexts = run_overlapping_triggers(OverlapPolicy::Drop);
+
+        assert_eq!(1, contexts.len());
+        assert_eq!("1", contexts[0]["TRIGGER_NAME"]);
+    }
+
+    #[test]
+    fn it_should_reply_to_every_coalesced_trigger() {
+        let (first_tx, first_rx) = mpsc::channel();
+        let (second_tx, second_rx) = mpsc::channel();
+
+        // Setup mock triggers.
+        let mut mock_trigger = MockTrigger::new();
+        mock_trigger.expect_listen().return_once(move |tx| {
+            tx.send(Some(HashMap::new().into()))?;
+            sleep(Duration::from_millis(20));
+            for reply in [first_tx, second_tx] {
+                tx.send(Some(TriggerMessage {
+                    context: HashMap::new(),
+                    reply: Some(reply),
+                }))?;
+            }
+            // Wait for the coalesced run, before terminating
+            sleep(Duration::from_millis(300));
+            tx.send(None)?;
+            Ok(())
+        });
+        let triggers: Vec<Box<dyn Trigger>> = vec![Box::new(mock_trigger)];
+
+        // Setup mock check, that takes some time.
+        let mut mock_check = MockCheck::new();
+        mock_check.expect_check().times(2).returning(|_| {
+            sleep(Duration::from_millis(100));
+            Ok(false)
+        });
+        let mut check: Box<dyn Check> = Box::new(mock_check);
+
+        let result = start_with_overlap(triggers, &mut check, &mut [], OverlapPolicy::Coalesce);
+        assert!(result.is_ok());
+
+        assert!(first_rx.recv().unwrap().is_success());
+        assert!(second_rx.recv().unwrap().is_success());
+    }
+
+    #[test]
+    fn it_should_not_run_pending_triggers_after_termination() {
+        let (reply_tx, reply_rx) = mpsc::channel();
+
+        // Setup mock triggers, that terminate while the first check is running.
+        let mut mock_trigger = MockTrigger::new();
+        mock_trigger.expect_listen().return_once(move |tx| {
+            tx.send(Some(HashMap::new().into()))?;
+            sleep(Duration::from_millis(20));
+            tx.send(Some(TriggerMessage {
+                context: HashMap::new(),
+                reply: Some(reply_tx),
+            }))?;
+            tx.send(None)?;
+            Ok(())
+        });
+        let triggers: Vec<Box<dyn Trigger>> = vec![Box::new(mock_trigger)];
+
+        // Setup mock check, that takes some time.
+        let mut mock_check = MockCheck::new();
+        mock_check.expect_check().times(1).returning(|_| {
+            sleep(Duration::from_millis(100));
+            Ok(false)
+        });
+        let mut check: Box<dyn Check> = Box::new(mock_check);
+
+        let result = start_with_overlap(triggers, &mut check, &mut [], OverlapPolicy::Queue);
+        assert!(result.is_ok());
+
+        let report = reply_rx.recv().unwrap();
+        assert!(!report.is_success());
+        assert!(report.actions.is_empty());
+    }
 
     #[test]
     fn it_should_call_once() {
         // Setup mock triggers.
         let mut mock_trigger = MockTrigger::new();
         mock_trigger.expect_listen().returning(|tx| {
-            tx.send(Some(HashMap::new()))?;
+            tx.send(Some(HashMap::new().into()))?;
             tx.send(None)?;
             Ok(())
         });
@@ -104,6 +692,8 @@ mod tests {
         // Setup mock action.
         let mut mock_action = MockAction::new();
         mock_action.expect_run().times(1).returning(|_| Ok(()));
+        mock_action.expect_name().returning(|| String::from("mock"));
+        mock_action.expect_is_relevant().return_const(true);
         let actions: &mut [Box<dyn Action>] = &mut [Box::new(mock_action)];
 
         let result = start(triggers, &mut check, actions);
@@ -115,7 +705,7 @@ mod tests {
         // Setup mock triggers.
         let mut mock_trigger = MockTrigger::new();
         mock_trigger.expect_listen().returning(|tx| {
-            tx.send(Some(HashMap::new()))?;
+            tx.send(Some(HashMap::new().into()))?;
             tx.send(None)?;
             Ok(())
         });
@@ -140,7 +730,7 @@ mod tests {
         // Setup mock triggers.
         let mut mock_trigger = MockTrigger::new();
         mock_trigger.expect_listen().returning(|tx| {
-            tx.send(Some(HashMap::new()))?;
+            tx.send(Some(HashMap::new().into()))?;
             tx.send(None)?;
             Ok(())
         });
@@ -163,6 +753,449 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn it_should_reply_with_the_report() {
+        let (reply_tx, reply_rx) = mpsc::channel();
+
+        // Setup mock triggers.
+        let mut mock_trigger = MockTrigger::new();
+        mock_trigger.expect_listen().return_once(move |tx| {
+            let mut context: Context = HashMap::new();
+            context.insert("GIT_COMMIT_SHA", String::from("abc123"));
+            tx.send(Some(TriggerMessage {
+                context,
+                reply: Some(reply_tx),
+            }))?;
+            tx.send(None)?;
+            Ok(())
+        });
+        let triggers: Vec<Box<dyn Trigger>> = vec![Box::new(mock_trigger)];
+
+        // Setup mock check.
+        let mut mock_check = MockCheck::new();
+        mock_check.expect_check().times(1).returning(|_| Ok(true));
+        let mut check: Box<dyn Check> = Box::new(mock_check);
+
+        // Setup mock actions, the second one failing.
+        let mut mock_action = MockAction::new();
+        mock_action.expect_run().times(1).returning(|_| Ok(()));
+        mock_action
+            .expect_name()
+            .returning(|| String::from("first"));
+        mock_action.expect_is_relevant().return_const(true);
+        let mut failing_action = MockAction::new();
+        failing_action
+            .expect_run()
+            .times(1)
+            .returning(|_| Err(ActionError::FailedAction(String::from("Testing purposes."))));
+        failing_action
+            .expect_name()
+            .returning(|| String::from("second"));
+        failing_action.expect_is_relevant().return_const(true);
+        let actions: &mut [Box<dyn Action>] =
+            &mut [Box::new(mock_action), Box::new(failing_action)];
+
+        let result = start(triggers, &mut check, actions);
+        assert!(result.is_ok());
+
+        let report = reply_rx.recv().unwrap();
+        assert!(report.updated);
+        assert!(!report.is_success());
+        assert_eq!(Some(String::from("abc123")), report.commit_sha);
+        assert_eq!(
+            vec![
+                ActionReport {
+                    name: String::from("first"),
+                    skipped: false,
+                    exit_code: Some(0),
+                    ..Default::default()
+                },
+                ActionReport {
+                    name: String::from("second"),
+                    skipped: false,
+                    error: Some(String::from("Testing purposes.")),
+                    ..Default::default()
+                }
+            ],
+            without_durations(report.actions)
+        );
+    }
+
+    #[test]
+    fn it_should_roll_back_failed_updates() {
+        let (reply_tx, reply_rx) = mpsc::channel();
+
+        // Setup mock triggers.
+        let mut mock_trigger = MockTrigger::new();
+        mock_trigger.expect_listen().return_once(move |tx| {
+            tx.send(Some(TriggerMessage {
+                context: HashMap::new(),
+                reply: Some(reply_tx),
+            }))?;
+            tx.send(None)?;
+            Ok(())
+        });
+        let triggers: Vec<Box<dyn Trigger>> = vec![Box::new(mock_trigger)];
+
+        // Setup mock check, that rolls back.
+        let mut mock_check = MockCheck::new();
+        mock_check.expect_check().times(1).returning(|_| Ok(true));
+        mock_check
+            .expect_rollback()
+            .times(1)
+            .returning(|_| Ok(true));
+        let mut check: Box<dyn Check> = Box::new(mock_check);
+
+        // Setup a failing action and a rollback action.
+        let mut failing_action = MockAction::new();
+        failing_action
+            .expect_run()
+            .times(1)
+            .returning(|_| Err(ActionError::FailedAction(String::from("Testing purposes."))));
+        failing_action
+            .expect_name()
+            .returning(|| String::from("failing"));
+        failing_action.expect_is_relevant().return_const(true);
+        let actions: &mut [Box<dyn Action>] = &mut [Box::new(failing_action)];
+        let mut rollback_action = MockAction::new();
+        rollback_action.expect_run().times(1).returning(|_| Ok(()));
+        rollback_action.expect_is_relevant().return_const(true);
+
+        let options = StartOptions {
+            rollback: RollbackPolicy::Actions(vec![Box::new(rollback_action)]),
+            ..Default::default()
+        };
+        let result = start_with_options(triggers, &mut check, actions, options);
+        assert!(result.is_ok());
+
+        let report = reply_rx.recv().unwrap();
+        assert!(!report.is_success());
+        assert!(report.rolled_back);
+    }
+
+    #[test]
+    fn it_should_not_roll_back_by_default() {
+        // Setup mock triggers.
+        let mut mock_trigger = MockTrigger::new();
+        mock_trigger.expect_listen().returning(|tx| {
+            tx.send(Some(HashMap::new().into()))?;
+            tx.send(None)?;
+            Ok(())
+        });
+        let triggers: Vec<Box<dyn Trigger>> = vec![Box::new(mock_trigger)];
+
+        // Setup mock check.
+        let mut mock_check = MockCheck::new();
+        mock_check.expect_check().times(1).returning(|_| Ok(true));
+        mock_check.expect_rollback().times(0);
+        let mut check: Box<dyn Check> = Box::new(mock_check);
+
+        // Setup a failing action.
+        let mut failing_action = MockAction::new();
+        failing_action
+            .expect_run()
+            .times(1)
+            .returning(|_| Err(ActionError::FailedAction(String::from("Testing purposes."))));
+        failing_action
+            .expect_name()
+            .returning(|| String::from("failing"));
+        failing_action.expect_is_relevant().return_const(true);
+        let actions: &mut [Box<dyn Action>] = &mut [Box::new(failing_action)];
+
+        let result = start(triggers, &mut check, actions);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn it_should_save_updates_and_errors_to_the_history() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let id = Alphanumeric.sample_string(&mut rand::rng(), 16);
+        let directory = format!("test_directories/{id}");
+
+        // Setup mock triggers, that fire three times.
+        let mut mock_trigger = MockTrigger::new();
+        mock_trigger.expect_listen().returning(|tx| {
+            for _ in 0..3 {
+                let context: Context = HashMap::from([("TRIGGER_NAME", String::from("TEST"))]);
+                tx.send(Some(context.into()))?;
+                sleep(Duration::from_millis(10));
+            }
+            tx.send(None)?;
+            Ok(())
+        });
+        let triggers: Vec<Box<dyn Trigger>> = vec![Box::new(mock_trigger)];
+
+        // Setup mock check, that updates the first time, and fails the third time.
+        let mut mock_check = MockCheck::new();
+        let mut runs = 0;
+        mock_check
+            .expect_check()
+            .times(3)
+            .returning(move |context| {
+                runs += 1;
+                context.insert("GIT_BEFORE_COMMIT_SHA", String::from("abc123"));
+                context.insert("GIT_COMMIT_SHA", String::from("def456"));
+                match runs {
+                    1 => Ok(true),
+                    2 => Ok(false),
+                    _ => Err(CheckError::FailedUpdate(String::from("cannot fetch"))),
+                }
+            });
+        let mut check: Box<dyn Check> = Box::new(mock_check);
+
+        // Setup mock action.
+        let mut mock_action = MockAction::new();
+        mock_action.expect_run().times(1).returning(|_| Ok(()));
+        mock_action.expect_name().returning(|| String::from("echo"));
+        mock_action.expect_is_relevant().return_const(true);
+        let actions: &mut [Box<dyn Action>] = &mut [Box::new(mock_action)];
+
+        let options = StartOptions {
+            overlap: OverlapPolicy::Queue,
+            history: Some(History::open(&directory)?),
+            ..Default::default()
+        };
+        start_with_options(triggers, &mut check, actions, options)?;
+
+        let records = History::new(&directory).read(10)?;
+        assert_eq!(2, records.len());
+        assert_eq!("TEST", records[0]["trigger"]);
+        assert_eq!("abc123", records[0]["before_commit_sha"]);
+        assert_eq!("def456", records[0]["commit_sha"]);
+        assert_eq!(true, records[0]["success"]);
+        assert_eq!("echo", records[0]["actions"][0]["name"]);
+        assert_eq!(0, records[0]["actions"][0]["exit_code"]);
+        assert_eq!(false, records[1]["success"]);
+        assert_eq!("failed while running: cannot fetch", records[1]["error"]);
+
+        std::fs::remove_dir_all(&directory)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_retry_the_release_if_it_failed() -> Result<(), Box<dyn std::error::Error>> {
+        let id = Alphanumeric.sample_string(&mut rand::rng(), 16);
+        let directory = format!("test_directories/{id}");
+
+        // Setup mock triggers, that fire twice.
+        let (reply_tx, reply_rx) = mpsc::channel::<RunReport>();
+        let mut mock_trigger = MockTrigger::new();
+        mock_trigger.expect_listen().returning(move |tx| {
+            for _ in 0..2 {
+                tx.send(Some(TriggerMessage {
+                    context: HashMap::new(),
+                    reply: Some(reply_tx.clone()),
+                }))?;
+                sleep(Duration::from_millis(10));
+            }
+            tx.send(None)?;
+            Ok(())
+        });
+        let triggers: Vec<Box<dyn Trigger>> = vec![Box::new(mock_trigger)];
+
+        // Setup mock check, that pulls the update once, but fails to export it the first time.
+        let mut mock_check = MockCheck::new();
+        let mut checks = 0;
+        mock_check
+            .expect_check()
+            .times(2)
+            .returning(move |context| {
+                checks += 1;
+                if checks == 1 {
+                    context.insert("GIT_COMMIT_SHA", String::from("abc123"));
+                }
+                Ok(checks == 1)
+            });
+        let mut exports = 0;
+        mock_check
+            .expect_export()
+            .times(2)
+            .returning(move |context, directory| {
+                exports += 1;
+                if exports == 1 {
+                    return Err(CheckError::FailedUpdate(String::from("Testing purposes.")));
+                }
+                std::fs::write(directory.join("version"), &context["GIT_COMMIT_SHA"]).unwrap();
+                Ok(true)
+            });
+        let mut check: Box<dyn Check> = Box::new(mock_check);
+
+        let options = StartOptions {
+            releases: Some(Releases::new(&directory)),
+            ..Default::default()
+        };
+        start_with_options(triggers, &mut check, &mut [], options)?;
+
+        let report = reply_rx.recv()?;
+        assert!(!report.is_success());
+        let report = reply_rx.recv()?;
+        assert!(report.is_success());
+        assert!(report.updated);
+        assert_eq!(Some(String::from("abc123")), report.commit_sha);
+
+        let releases = Releases::new(&directory);
+        assert_eq!(Some(String::from("abc123")), releases.current());
+
+        std::fs::remove_dir_all(&directory)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_switch_releases_only_after_successful_actions(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let id = Alphanumeric.sample_string(&mut rand::rng(), 16);
+        let directory = format!("test_directories/{id}");
+
+        // Setup mock triggers, that fire twice.
+        let mut mock_trigger = MockTrigger::new();
+        mock_trigger.expect_listen().returning(|tx| {
+            for _ in 0..2 {
+                tx.send(Some(HashMap::new().into()))?;
+                sleep(Duration::from_millis(10));
+            }
+            tx.send(None)?;
+            Ok(())
+        });
+        let triggers: Vec<Box<dyn Trigger>> = vec![Box::new(mock_trigger)];
+
+        // Setup mock check, that updates to a new commit every time.
+        let mut mock_check = MockCheck::new();
+        let mut commits = vec!["def456", "abc123"];
+        mock_check
+            .expect_check()
+            .times(2)
+            .returning(move |context| {
+                context.insert("GIT_COMMIT_SHA", commits.pop().unwrap().to_string());
+                Ok(true)
+            });
+        mock_check
+            .expect_export()
+            .times(2)
+            .returning(|_, _| Ok(true));
+        mock_check.expect_rollback().times(0);
+        let mut check: Box<dyn Check> = Box::new(mock_check);
+
+        // Setup mock action, that runs in the release and fails the second time.
+        let mut mock_action = MockAction::new();
+        let mut runs = 0;
+        mock_action.expect_run().times(2).returning(move |context| {
+            assert!(context["RELEASE_DIRECTORY"].ends_with(&context["GIT_COMMIT_SHA"]));
+            runs += 1;
+            if runs == 1 {
+                Ok(())
+            } else {
+                Err(ActionError::FailedAction(String::from("Testing purposes.")))
+            }
+        });
+        mock_action
+            .expect_name()
+            .returning(|| String::from("build"));
+        mock_action.expect_is_relevant().return_const(true);
+        mock_action
+            .expect_runs_after_activation()
+            .return_const(false);
+        mock_action.expect_directory_in_use().return_const(None);
+
+        // Setup mock process, that restarts only after switching to the release.
+        let current = format!("{directory}/current");
+        let mut mock_process = MockAction::new();
+        mock_process
+            .expect_run()
+            .times(1)
+            .returning(move |context| {
+                let release = std::fs::canonicalize(&current).unwrap();
+                assert!(release.ends_with(&context["GIT_COMMIT_SHA"]));
+                Ok(())
+            });
+        mock_process
+            .expect_name()
+            .returning(|| String::from("process"));
+        mock_process.expect_is_relevant().return_const(true);
+        mock_process
+            .expect_runs_after_activation()
+            .return_const(true);
+        mock_process.expect_directory_in_use().return_const(None);
+        let actions: &mut [Box<dyn Action>] = &mut [Box::new(mock_action), Box::new(mock_process)];
+
+        let options = StartOptions {
+            overlap: OverlapPolicy::Queue,
+            rollback: RollbackPolicy::Rerun,
+            releases: Some(Releases::new(&directory)),
+            ..Default::default()
+        };
+        start_with_options(triggers, &mut check, actions, options)?;
+
+        let releases = Releases::new(&directory);
+        assert_eq!(Some(String::from("abc123")), releases.current());
+        assert!(!releases.release_path("def456").exists());
+
+        std::fs::remove_dir_all(&directory)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_skip_irrelevant_actions() {
+        let (reply_tx, reply_rx) = mpsc::channel::<RunReport>();
+
+        // Setup mock triggers.
+        let mut mock_trigger = MockTrigger::new();
+        mock_trigger.expect_listen().returning(move |tx| {
+            tx.send(Some(TriggerMessage {
+                context: HashMap::new(),
+                reply: Some(reply_tx.clone()),
+            }))?;
+            tx.send(None)?;
+            Ok(())
+        });
+        let triggers: Vec<Box<dyn Trigger>> = vec![Box::new(mock_trigger)];
+
+        // Setup mock check.
+        let mut mock_check = MockCheck::new();
+        mock_check.expect_check().times(1).returning(|_| Ok(true));
+        let mut check: Box<dyn Check> = Box::new(mock_check);
+
+        // Setup mock actions, the first one skipped.
+        let mut skipped_action = MockAction::new();
+        skipped_action.expect_run().times(0);
+        skipped_action
+            .expect_name()
+            .returning(|| String::from("first"));
+        skipped_action.expect_is_relevant().return_const(false);
+        let mut mock_action = MockAction::new();
+        mock_action.expect_run().times(1).returning(|_| Ok(()));
+        mock_action
+            .expect_name()
+            .returning(|| String::from("second"));
+        mock_action.expect_is_relevant().return_const(true);
+        let actions: &mut [Box<dyn Action>] =
+            &mut [Box::new(skipped_action), Box::new(mock_action)];
+
+        let result = start(triggers, &mut check, actions);
+        assert!(result.is_ok());
+
+        let report = reply_rx.recv().unwrap();
+        assert!(report.is_success());
+        assert_eq!(
+            vec![
+                ActionReport {
+                    name: String::from("first"),
+                    skipped: true,
+                    ..Default::default()
+                },
+                ActionReport {
+                    name: String::from("second"),
+                    skipped: false,
+                    exit_code: Some(0),
+                    ..Default::default()
+                }
+            ],
+            without_durations(report.actions)
+        );
+    }
+
     #[test]
     fn it_should_fail_without_triggers() {
         // Setup empty triggers.
@@ -0,0 +1,350 @@
+use super::{Trigger, TriggerError, TriggerMessage};
+use std::{io, path::PathBuf, sync::mpsc::Sender, time::Duration};
+use thiserror::Error;
+
+const TRIGGER_NAME: &str = "FILE";
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// A trigger that runs the checks when a file or directory changes.
+///
+/// It uses inotify, so this is only supported on Linux. To avoid running on every write,
+/// the events are collected until there is no change for the debounce duration.
+pub struct FileTrigger {
+    paths: Vec<PathBuf>,
+    debounce: Duration,
+}
+
+/// Custom error describing the error cases for the FileTrigger.
+#[derive(Debug, Error)]
+pub enum FileError {
+    /// Cannot start inotify.
+    #[error("cannot start watching files: {0}")]
+    CantInitialize(io::Error),
+    /// Cannot watch the file or directory, it probably doesn't exist.
+    #[error("cannot watch {0}: {1}")]
+    CantWatch(String, io::Error),
+    /// Cannot read the events from inotify.
+    #[error("cannot read file events: {0}")]
+    FailedReading(io::Error),
+    /// Watching files is only supported on Linux.
+    #[error("watching files is only supported on Linux")]
+    NotSupported,
+    /// Cannot send trigger with Sender. This usually because the receiver is dropped.
+    #[error("cannot trigger changes, receiver hang up")]
+    ReceiverHangup(#[from] std::sync::mpsc::SendError<Option<TriggerMessage>>),
+}
+
+impl From<FileError> for TriggerError {
+    fn from(val: FileError) -> Self {
+        match val {
+            FileError::CantWatch(_, _) | FileError::NotSupported => {
+                TriggerError::Misconfigured(val.to_string())
+            }
+            FileError::CantInitialize(_) | FileError::FailedReading(_) => {
+                TriggerError::FailedTrigger(val.to_string())
+            }
+            FileError::ReceiverHangup(s) => TriggerError::ReceiverHangup(s),
+        }
+    }
+}
+
+impl FileTrigger {
+    /// Creates a new FileTrigger watching the files or directories.
+    pub fn new(paths: Vec<String>) -> Self {
+        Self {
+            paths: paths.into_iter().map(PathBuf::from).collect(),
+            debounce: DEBOUNCE,
+        }
+    }
+
+    /// Wait this long after the last event before triggering.
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    #[cfg(target_os = "linux")]
+    fn listen_inner(&self, tx: Sender<Option<TriggerMessage>>) -> Result<(), FileError> {
+        use crate::context::Context;
+        use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+        use log::{debug, info, trace, warn};
+        use std::{
+            collections::{HashMap, HashSet},
+            ffi::OsString,
+            fs,
+            path::Path,
+            thread::sleep,
+            time::Instant,
+        };
+
+        // A watched directory, either with all of the files or only the given ones
+        enum Watched {
+            Directory(PathBuf),
+            Files(PathBuf, HashSet<OsString>),
+        }
+
+        const MASK: WatchMask = WatchMask::MODIFY
+            .union(WatchMask::CLOSE_WRITE)
+            .union(WatchMask::ATTRIB)
+            .union(WatchMask::CREATE)
+            .union(WatchMask::DELETE)
+            .union(WatchMask::MOVED_FROM)
+            .union(WatchMask::MOVED_TO);
+
+        fn watch_directory(
+            inotify: &Inotify,
+            watched: &mut HashMap<WatchDescriptor, Watched>,
+            directory: &Path,
+        ) -> Result<(), FileError> {
+            let wd = inotify.watches().add(directory, MASK).map_err(|err| {
+                FileError::CantWatch(directory.to_string_lossy().to_string(), err)
+            })?;
+            watched.insert(wd, Watched::Directory(directory.to_path_buf()));
+
+            // Inotify is not recursive, so we have to watch every subdirectory
+            if let Ok(entries) = fs::read_dir(directory) {
+                for entry in entries.flatten() {
+                    // The subdirectory might have been removed since reading the directory
+                    if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                        if let Err(err) = watch_directory(inotify, watched, &entry.path()) {
+                            warn!("Failed watching subdirectory: {err}.");
+                        }
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        let mut inotify = Inotify::init().map_err(FileError::CantInitialize)?;
+        let mut watched: HashMap<WatchDescriptor, Watched> = HashMap::new();
+        for path in &self.paths {
+            if path.is_dir() {
+                watch_directory(&inotify, &mut watched, path)?;
+            } else {
+                // Watch the parent directory, so the file can be created, deleted or replaced
+                let directory = match path.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                    _ => PathBuf::from("."),
+                };
+                let file_name = path.file_name().map(|f| f.to_os_string()).ok_or_else(|| {
+                    FileError::CantWatch(
+                        path.to_string_lossy().to_string(),
+                        io::Error::from(io::ErrorKind::InvalidInput),
+                    )
+                })?;
+                let wd = inotify
+                    .watches()
+                    .add(&directory, MASK)
+                    .map_err(|err| FileError::CantWatch(path.to_string_lossy().to_string(), err))?;
+                match watched
+                    .entry(wd)
+                    .or_insert_with(|| Watched::Files(directory, HashSet::new()))
+                {
+                    Watched::Files(_, files) => {
+                        files.insert(file_name);
+                    }
+                    Watched::Directory(_) => {}
+                }
+            }
+        }
+
+        info!(
+            "Starting to watch {}.",
+            self.paths
+                .iter()
+                .map(|p| p.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let mut buffer = [0; 4096];
+        loop {
+            let mut changed_paths: Vec<String> = vec![];
+            let mut last_event: Option<Instant> = None;
+
+            // Block until the first event, then collect events until the debounce passes
+            loop {
+                let events = match last_event {
+                    None => inotify.read_events_blocking(&mut buffer),
+                    Some(last_event) => {
+                        let elapsed = last_event.elapsed();
+                        if elapsed >= self.debounce {
+                            break;
+                        }
+                        sleep((self.debounce - elapsed).min(Duration::from_millis(50)));
+                        inotify.read_events(&mut buffer)
+                    }
+                };
+                let events = match events {
+                    Ok(events) => events,
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+                    Err(err) => return Err(FileError::FailedReading(err)),
+                };
+
+                let mut new_directories = vec![];
+                for event in events {
+                    if event.mask.contains(EventMask::IGNORED) {
+                        watched.remove(&event.wd);
+                        continue;
+                    }
+                    let path = match watched.get(&event.wd) {
+                        Some(Watched::Directory(directory)) => {
+                            let path = event
+                                .name
+                                .map(|name| directory.join(name))
+                                .unwrap_or(directory.clone());
+                            if event.mask.contains(EventMask::ISDIR)
+                                && (event.mask.contains(EventMask::CREATE)
+                                    || event.mask.contains(EventMask::MOVED_TO))
+                            {
+                                new_directories.push(path.clone());
+                            }
+                            path
+                        }
+                        Some(Watched::Files(directory, files)) => match event.name {
+                            Some(name) if files.contains(name) => directory.join(name),
+                            _ => continue,
+                        },
+                        None => continue,
+                    };
+
+                    let path = path.to_string_lossy().to_string();
+                    trace!("File {path} changed ({:?}).", event.mask);
+                    if !changed_paths.contains(&path) {
+                        changed_paths.push(path);
+                    }
+                    last_event = Some(Instant::now());
+                }
+
+                for directory in new_directories {
+                    if let Err(err) = watch_directory(&inotify, &mut watched, &directory) {
+                        warn!("Failed watching new directory: {err}.");
+                    }
+                }
+            }
+
+            debug!("Files changed: {}.", changed_paths.join(", "));
+            let context: Context = HashMap::from([
+                ("TRIGGER_NAME", TRIGGER_NAME.to_string()),
+                ("FILE_PATH", changed_paths[0].clone()),
+                ("FILE_PATHS", changed_paths.join("\n")),
+            ]);
+            tx.send(Some(context.into()))?;
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn listen_inner(&self, _tx: Sender<Option<TriggerMessage>>) -> Result<(), FileError> {
+        Err(FileError::NotSupported)
+    }
+}
+
+impl Trigger for FileTrigger {
+    /// Starts a trigger that watches the files with inotify, and triggers on changes.
+    fn listen(&self, tx: Sender<Option<TriggerMessage>>) -> Result<(), TriggerError> {
+        self.listen_inner(tx)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+    use rand::distr::{Alphanumeric, SampleString};
+    use std::{error::Error, fs, sync::mpsc, thread, thread::sleep};
+
+    fn get_random_id() -> String {
+        Alphanumeric.sample_string(&mut rand::rng(), 16)
+    }
+
+    fn start_trigger(paths: Vec<String>) -> mpsc::Receiver<Option<TriggerMessage>> {
+        let mut trigger = FileTrigger::new(paths);
+        trigger.set_debounce(Duration::from_millis(100));
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        thread::spawn(move || {
+            let _ = trigger.listen_inner(tx);
+        });
+
+        // Sleep for the watches to be added.
+        sleep(Duration::from_millis(100));
+
+        rx
+    }
+
+    #[test]
+    fn it_should_trigger_when_the_file_is_touched() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        fs::create_dir(&local)?;
+        let marker = format!("{local}/deploy");
+
+        let rx = start_trigger(vec![marker.clone()]);
+
+        // Other files in the directory should not trigger
+        fs::write(format!("{local}/other"), "other")?;
+        assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+
+        fs::write(&marker, "1")?;
+        let context = rx.recv_timeout(Duration::from_secs(1))?.unwrap().context;
+        assert_eq!(TRIGGER_NAME, context.get("TRIGGER_NAME").unwrap());
+        assert_eq!(&marker, context.get("FILE_PATH").unwrap());
+
+        let _ = fs::remove_dir_all(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_debounce_the_events_in_a_directory() -> Result<(), Box<dyn Error>> {
+        let id = get_random_id();
+        let local = format!("test_directories/{id}");
+        fs::create_dir_all(format!("{local}/sub"))?;
+
+        let rx = start_trigger(vec![local.clone()]);
+
+        // A burst of changes should only trigger once
+        for i in 0..5 {
+            fs::write(format!("{local}/{i}"), "1")?;
+            sleep(Duration::from_millis(20));
+        }
+        fs::write(format!("{local}/sub/5"), "1")?;
+
+        let context = rx.recv_timeout(Duration::from_secs(1))?.unwrap().context;
+        assert_eq!(format!("{local}/0"), *context.get("FILE_PATH").unwrap());
+        assert_eq!(
+            (0..5)
+                .map(|i| format!("{local}/{i}"))
+                .chain([format!("{local}/sub/5")])
+                .collect::<Vec<_>>()
+                .join("\n"),
+            *context.get("FILE_PATHS").unwrap()
+        );
+        assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+
+        // New directories should be watched as well
+        fs::create_dir(format!("{local}/new"))?;
+        let _ = rx.recv_timeout(Duration::from_secs(1))?;
+        fs::write(format!("{local}/new/6"), "1")?;
+        let context = rx.recv_timeout(Duration::from_secs(1))?.unwrap().context;
+        assert_eq!(format!("{local}/new/6"), *context.get("FILE_PATH").unwrap());
+
+        let _ = fs::remove_dir_all(&local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fail_if_the_directory_does_not_exist() {
+        let trigger = FileTrigger::new(vec![String::from("/path/to/nowhere/deploy")]);
+        let (tx, _rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        let result = trigger.listen_inner(tx);
+        assert!(
+            matches!(result, Err(FileError::CantWatch(_, _))),
+            "{result:?} should be CantWatch"
+        );
+    }
+}
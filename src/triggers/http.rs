@@ -1,19 +1,57 @@
-use super::{Trigger, TriggerError};
+use super::{Trigger, TriggerError, TriggerMessage};
 use crate::context::Context;
-use log::{debug, info};
-use std::{collections::HashMap, sync::mpsc::Sender};
+use log::{debug, info, warn};
+use payload::{parse_webhook, PushEvent, WebhookEvent};
+use signature::verify_signature;
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    path::PathBuf,
+    sync::mpsc::{self, Sender},
+    time::Duration,
+};
 use thiserror::Error;
-use tiny_http::{Response, Server};
+use tiny_http::{Header, Request, Response, Server};
+use tls::{TlsCertificate, TlsFiles};
+
+mod payload;
+mod signature;
+mod tls;
 
 const TRIGGER_NAME: &str = "HTTP";
+const TLS_RELOAD_INTERVAL: Duration = Duration::from_secs(10);
 
 /// A trigger that runs on an HTTP request.
 ///
 /// This could be used to trigger checks from git remotes (e.g. GitHub, GitLab) with webhooks.
 /// Given that your server can be reached from the outside, you can pass your server's hostname
 /// or IP address and have actions running on git changes immediately.
+///
+/// If a secret is set, only the requests signed with it are accepted. It supports the
+/// signatures of GitHub (`X-Hub-Signature-256`), GitLab (`X-Gitlab-Token`) and
+/// Gitea or Forgejo (`X-Gitea-Signature`).
+///
+/// The push events of GitHub, GitLab, Gitea and Bitbucket webhooks are parsed: pushes to other
+/// branches and non-push events (e.g. pings) are ignored.
+///
+/// The allowed paths and methods can be restricted, to avoid triggering on every request
+/// (e.g. from crawlers or health checks).
+///
+/// If a certificate and private key is set, the server serves HTTPS.
+/// The files are reloaded if they change, so the certificates can be renewed without restarting.
+///
+/// In synchronous mode the response waits until the check and the actions finish, and reports
+/// the results, so the webhook deliveries show if the deployment was successful.
 pub struct HttpTrigger {
     http: String,
+    secret: Option<String>,
+    branch: Option<String>,
+    tags: bool,
+    paths: Vec<String>,
+    methods: Vec<String>,
+    tls: Option<TlsFiles>,
+    tls_reload_interval: Duration,
+    sync: bool,
 }
 
 /// Custom error describing the error cases for the HttpTrigger.
@@ -23,9 +61,15 @@ pub enum HttpError {
     /// or the port was already allocated.
     #[error("cannot start server on {0}")]
     CantStartServer(String),
+    /// Cannot read the certificate or the private key file.
+    #[error("cannot load certificate {0} ({1})")]
+    CantLoadCertificate(String, std::io::Error),
+    /// The certificate or the private key cannot be used, e.g. it is half-written or they don't match.
+    #[error("the certificate is invalid ({0})")]
+    InvalidCertificate(String),
     /// Cannot send trigger with Sender. This usually because the receiver is dropped.
     #[error("cannot trigger changes, receiver hang up")]
-    ReceiverHangup(#[from] std::sync::mpsc::SendError<Option<Context>>),
+    ReceiverHangup(#[from] std::sync::mpsc::SendError<Option<TriggerMessage>>),
     /// Failed to send response.
     #[error("failed to send response")]
     FailedResponse(#[from] std::io::Error),
@@ -35,44 +79,268 @@ impl From<HttpError> for TriggerError {
     fn from(val: HttpError) -> Self {
         match val {
             HttpError::CantStartServer(s) => TriggerError::Misconfigured(s),
+            HttpError::CantLoadCertificate(_, _) | HttpError::InvalidCertificate(_) => {
+                TriggerError::Misconfigured(val.to_string())
+            }
             HttpError::ReceiverHangup(s) => TriggerError::ReceiverHangup(s),
             HttpError::FailedResponse(s) => TriggerError::FailedTrigger(s.to_string()),
         }
     }
 }
 
+fn find_header<'a>(headers: &'a [Header], name: &'static str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
 impl HttpTrigger {
     /// Create an new HTTP trigger with a HTTP url. It accepts an address as a string,
     /// for example "0.0.0.0:1234".
     pub fn new(http: String) -> Self {
-        Self { http }
+        Self {
+            http,
+            secret: None,
+            branch: None,
+            tags: false,
+            paths: vec![],
+            methods: vec![],
+            tls: None,
+            tls_reload_interval: TLS_RELOAD_INTERVAL,
+            sync: false,
+        }
+    }
+
+    /// Wait for the check and the actions to finish before responding. The response
+    /// contains the results in JSON and returns 500 status code if anything failed.
+    pub fn set_sync(&mut self, sync: bool) {
+        self.sync = sync;
+    }
+
+    /// Serve HTTPS with the certificate and private key in PEM format.
+    pub fn set_tls(&mut self, certificate: String, private_key: String) {
+        self.tls = Some(TlsFiles::new(
+            PathBuf::from(certificate),
+            PathBuf::from(private_key),
+        ));
     }
 
-    fn listen_inner(&self, tx: Sender<Option<Context>>) -> Result<(), HttpError> {
-        let listener =
-            Server::http(&self.http).map_err(|_| HttpError::CantStartServer(self.http.clone()))?;
-        info!("Listening on {}...", self.http);
-        for request in listener.incoming_requests() {
-            debug!("Received request on {} {}", request.method(), request.url());
+    /// Set a shared secret, that the requests have to be signed with.
+    pub fn set_secret(&mut self, secret: String) {
+        self.secret = Some(secret);
+    }
 
-            let context: Context = HashMap::from([
-                ("TRIGGER_NAME", TRIGGER_NAME.to_string()),
-                ("HTTP_METHOD", request.method().to_string()),
-                ("HTTP_URL", request.url().to_string()),
-            ]);
-            tx.send(Some(context)).map_err(HttpError::from)?;
+    /// Set the branch that the repository is on (e.g. "refs/heads/main"). Webhook
+    /// push events on other branches are ignored.
+    pub fn set_branch(&mut self, branch: String) {
+        self.branch = Some(branch);
+    }
 
-            request.respond(Response::from_string("OK"))?;
+    /// Accept the webhook push events of tags (e.g. when updating on tags). If a branch
+    /// is set, the tag pushes are ignored by default.
+    pub fn set_tags(&mut self, tags: bool) {
+        self.tags = tags;
+    }
+
+    /// Set the paths that are allowed to trigger (e.g. "/hooks/deploy"). Requests on other
+    /// paths return 404 status code. If it is empty, every path is allowed.
+    pub fn set_paths(&mut self, paths: Vec<String>) {
+        self.paths = paths;
+    }
+
+    /// Set the methods that are allowed to trigger (e.g. "POST"). Requests with other
+    /// methods return 405 status code. If it is empty, every method is allowed.
+    pub fn set_methods(&mut self, methods: Vec<String>) {
+        self.methods = methods;
+    }
+
+    fn is_relevant_push(&self, push: &PushEvent) -> bool {
+        match &self.branch {
+            Some(_) if push.git_ref.starts_with("refs/tags/") => self.tags,
+            Some(branch) => &push.git_ref == branch,
+            None => true,
+        }
+    }
+
+    fn handle_request(
+        &self,
+        request: &mut Request,
+        tx: &Sender<Option<TriggerMessage>>,
+    ) -> Result<Response<Cursor<Vec<u8>>>, HttpError> {
+        let path = request.url().split('?').next().unwrap_or_default();
+        if !self.paths.is_empty() && !self.paths.iter().any(|p| p == path) {
+            debug!("Path {path} is not allowed, ignoring.");
+            return Ok(Response::from_string("Not Found").with_status_code(404));
+        }
+
+        let method = request.method().as_str();
+        if !self.methods.is_empty() && !self.methods.iter().any(|m| m.eq_ignore_ascii_case(method))
+        {
+            debug!("Method {method} is not allowed, ignoring.");
+            return Ok(Response::from_string("Method Not Allowed").with_status_code(405));
+        }
+
+        let mut body = vec![];
+        if let Err(err) = request.as_reader().read_to_end(&mut body) {
+            warn!("Failed reading the body of the request: {err}.");
+            return Ok(Response::from_string("Bad Request").with_status_code(400));
+        }
+
+        if let Some(secret) = &self.secret {
+            if let Some(scheme) = verify_signature(secret, request.headers(), &body) {
+                debug!("Request is signed correctly with the {scheme:?} scheme.");
+            } else {
+                warn!(
+                    "Request on {} {} is not signed with the secret, rejecting.",
+                    request.method(),
+                    request.url()
+                );
+                return Ok(Response::from_string("Unauthorized").with_status_code(401));
+            }
+        }
+
+        let mut context: Context = HashMap::from([
+            ("TRIGGER_NAME", TRIGGER_NAME.to_string()),
+            ("HTTP_METHOD", request.method().to_string()),
+            ("HTTP_URL", request.url().to_string()),
+        ]);
+
+        match parse_webhook(request.headers(), &body) {
+            Some(Ok(WebhookEvent::Push(pushes))) => {
+                let Some(push) = pushes.into_iter().find(|push| self.is_relevant_push(push)) else {
+                    debug!("Push event is not on the current branch, ignoring.");
+                    return Ok(Response::from_string("Ignored").with_status_code(200));
+                };
+                debug!("Received push event on {} to {}.", push.git_ref, push.after);
+                context.insert("HTTP_REF", push.git_ref);
+                context.insert("HTTP_COMMIT_SHA", push.after);
+                context.insert("HTTP_PUSHER", push.pusher);
+                context.insert("HTTP_REPOSITORY_NAME", push.repository);
+            }
+            Some(Ok(WebhookEvent::Other(event))) => {
+                debug!("Received {event} event, ignoring.");
+                return Ok(Response::from_string("Ignored").with_status_code(200));
+            }
+            Some(Err(event)) => {
+                warn!("Failed parsing the payload of {event} event, ignoring.");
+                return Ok(Response::from_string("Bad Request").with_status_code(400));
+            }
+            None => {}
+        }
+
+        if !self.sync {
+            tx.send(Some(context.into())).map_err(HttpError::from)?;
+            return Ok(Response::from_string("OK").with_status_code(200));
+        }
+
+        // Wait for the check and the actions to finish and report the results
+        let (reply_tx, reply_rx) = mpsc::channel();
+        tx.send(Some(TriggerMessage {
+            context,
+            reply: Some(reply_tx),
+        }))
+        .map_err(HttpError::from)?;
+        let Ok(report) = reply_rx.recv() else {
+            debug!("The check was not run for this request.");
+            return Ok(Response::from_string("Accepted").with_status_code(202));
+        };
+
+        let status = if report.is_success() { 200 } else { 500 };
+        let mut response = Response::from_data(report.to_json()).with_status_code(status);
+        if let Ok(header) = Header::from_bytes("Content-Type", "application/json") {
+            response.add_header(header);
+        }
+
+        Ok(response)
+    }
+
+    /// Restart the server with the changed certificate. If the new certificate cannot be used
+    /// (e.g. it is half-written), the running server is kept until the files change again.
+    fn reload_server(
+        &self,
+        tls: &TlsFiles,
+        certificate: &mut TlsCertificate,
+        listener: Server,
+    ) -> Result<Server, HttpError> {
+        let new_certificate = match tls
+            .load()
+            .and_then(|new_certificate| new_certificate.validate().map(|_| new_certificate))
+        {
+            Ok(new_certificate) => new_certificate,
+            Err(err) => {
+                warn!("The changed certificate cannot be used, keeping the old one: {err}.");
+                return Ok(listener);
+            }
+        };
+
+        info!(
+            "The certificate has changed, restarting the server on {}.",
+            self.http
+        );
+        drop(listener);
+        match new_certificate.start_server(&self.http) {
+            Ok(listener) => {
+                *certificate = new_certificate;
+                Ok(listener)
+            }
+            Err(err) => {
+                warn!("Failed restarting the server with the new certificate, restarting with the old one: {err}.");
+                certificate.start_server(&self.http)
+            }
+        }
+    }
+
+    fn listen_inner(&self, tx: Sender<Option<TriggerMessage>>) -> Result<(), HttpError> {
+        let mut certificate = self.tls.as_ref().map(TlsFiles::load).transpose()?;
+        let mut listener = match &certificate {
+            Some(certificate) => certificate.start_server(&self.http)?,
+            None => Server::http(&self.http)
+                .map_err(|_| HttpError::CantStartServer(self.http.clone()))?,
+        };
+        let mut tls_modified = self.tls.as_ref().and_then(TlsFiles::modified);
+        info!(
+            "Listening on {}{}...",
+            self.http,
+            if self.tls.is_some() {
+                " with HTTPS"
+            } else {
+                ""
+            }
+        );
+        loop {
+            if let Some(mut request) = listener.recv_timeout(self.tls_reload_interval)? {
+                debug!("Received request on {} {}", request.method(), request.url());
+
+                let response = self.handle_request(&mut request, &tx)?;
+                // The client might have closed the connection, keep listening for the next ones
+                if let Err(err) = request.respond(response) {
+                    warn!("Failed responding to the request: {err}.");
+                }
+            }
+
+            if let (Some(tls), Some(certificate)) = (&self.tls, &mut certificate) {
+                let modified = tls.modified();
+                if modified.is_some() && modified != tls_modified {
+                    // Retry only when the files change again, if the certificate is invalid
+                    tls_modified = modified;
+                    listener = self.reload_server(tls, certificate, listener)?;
+                }
+            }
         }
-        Ok(())
     }
 }
 
 impl Trigger for HttpTrigger {
     /// Starts a minimal HTTP 1.1 server, that triggers on every request.
     ///
-    /// Every method and every URL triggers and returns 200 status code with plaintext "OK".
-    fn listen(&self, tx: Sender<Option<Context>>) -> Result<(), TriggerError> {
+    /// Every method and every URL triggers and returns 200 status code with plaintext "OK",
+    /// unless the paths or methods are restricted, in which case the others return 404
+    /// or 405 status code.
+    /// If a secret is set, the requests without a valid signature return 401 status code
+    /// and don't trigger. Webhook events that are not pushes to the current branch
+    /// return 200 status code with "Ignored" and don't trigger.
+    fn listen(&self, tx: Sender<Option<TriggerMessage>>) -> Result<(), TriggerError> {
         self.listen_inner(tx)?;
 
         Ok(())
@@ -82,8 +350,11 @@ impl Trigger for HttpTrigger {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::start::{ActionReport, RunReport};
     use std::{
         error::Error,
+        io::Write,
+        net::TcpStream,
         sync::mpsc,
         thread::{self, sleep},
         time::Duration,
@@ -93,12 +364,17 @@ mod tests {
     fn it_should_be_created_from_http_url() {
         let trigger = HttpTrigger::new(String::from("0.0.0.0:1234"));
         assert_eq!("0.0.0.0:1234", &trigger.http);
+        assert_eq!(None, trigger.secret);
+        assert!(trigger.paths.is_empty());
+        assert!(trigger.methods.is_empty());
+        assert_eq!(None, trigger.tls);
+        assert!(!trigger.sync);
     }
 
     #[test]
     fn it_should_return_ok_on_every_request() -> Result<(), Box<dyn Error>> {
         let trigger = HttpTrigger::new(String::from("0.0.0.0:10101"));
-        let (tx, rx) = mpsc::channel::<Option<Context>>();
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
 
         thread::spawn(move || {
             let _ = trigger.listen_inner(tx);
@@ -116,13 +392,13 @@ mod tests {
         assert_eq!("OK", result.into_body().read_to_string()?);
 
         let msg = rx.recv()?;
-        let context = msg.unwrap();
+        let context = msg.unwrap().context;
         assert_eq!(TRIGGER_NAME, context.get("TRIGGER_NAME").unwrap());
         assert_eq!("GET", context.get("HTTP_METHOD").unwrap());
         assert_eq!("/", context.get("HTTP_URL").unwrap());
 
         let msg = rx.recv()?;
-        let context = msg.unwrap();
+        let context = msg.unwrap().context;
         assert_eq!(TRIGGER_NAME, context.get("TRIGGER_NAME").unwrap());
         assert_eq!("POST", context.get("HTTP_METHOD").unwrap());
         assert_eq!("/trigger", context.get("HTTP_URL").unwrap());
@@ -130,11 +406,445 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn it_should_keep_listening_if_the_client_disconnects() -> Result<(), Box<dyn Error>> {
+        let trigger = HttpTrigger::new(String::from("0.0.0.0:10111"));
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        thread::spawn(move || {
+            let _ = trigger.listen_inner(tx);
+        });
+
+        // Sleep for the HTTP server to start up.
+        sleep(Duration::from_millis(100));
+
+        // Reset the connection while the body is read, by closing it without reading the
+        // "100 Continue" response
+        let mut stream = TcpStream::connect("localhost:10111")?;
+        stream.write_all(
+            b"POST / HTTP/1.1\r\nHost: localhost\r\nExpect: 100-continue\r\nContent-Length: 100\r\n\r\n",
+        )?;
+        sleep(Duration::from_millis(100));
+        drop(stream);
+
+        // The server should still answer, fail instead of waiting if it stopped
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(5)))
+            .build()
+            .new_agent();
+        let result = agent.get("http://localhost:10111").call()?;
+        assert_eq!(200, result.status());
+
+        // Only the complete request should trigger
+        let msg = rx.recv()?;
+        assert_eq!("GET", msg.unwrap().context.get("HTTP_METHOD").unwrap());
+        assert!(rx.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_reject_requests_without_valid_signature() -> Result<(), Box<dyn Error>> {
+        let mut trigger = HttpTrigger::new(String::from("0.0.0.0:10103"));
+        trigger.set_secret(String::from("secret"));
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        thread::spawn(move || {
+            let _ = trigger.listen_inner(tx);
+        });
+
+        // Sleep for the HTTP server to start up.
+        sleep(Duration::from_millis(100));
+
+        let result = ureq::post("http://localhost:10103").send("{}");
+        assert!(
+            matches!(result, Err(ureq::Error::StatusCode(401))),
+            "{result:?} should be 401"
+        );
+
+        let result = ureq::post("http://localhost:10103")
+            .header("X-Hub-Signature-256", "sha256=0000")
+            .send("{}");
+        assert!(
+            matches!(result, Err(ureq::Error::StatusCode(401))),
+            "{result:?} should be 401"
+        );
+
+        let result = ureq::post("http://localhost:10103")
+            .header("X-Gitlab-Token", "not-the-secret")
+            .send("{}");
+        assert!(
+            matches!(result, Err(ureq::Error::StatusCode(401))),
+            "{result:?} should be 401"
+        );
+
+        // Nothing should be sent to the channel
+        assert!(rx.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_accept_requests_with_valid_signature() -> Result<(), Box<dyn Error>> {
+        let mut trigger = HttpTrigger::new(String::from("0.0.0.0:10104"));
+        trigger.set_secret(String::from("It's a Secret to Everybody"));
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        thread::spawn(move || {
+            let _ = trigger.listen_inner(tx);
+        });
+
+        // Sleep for the HTTP server to start up.
+        sleep(Duration::from_millis(100));
+
+        // Example from https://docs.github.com/en/webhooks/using-webhooks/validating-webhook-deliveries
+        let signature = "757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        let result = ureq::post("http://localhost:10104")
+            .header("X-Hub-Signature-256", &format!("sha256={signature}"))
+            .send("Hello, World!")?;
+        assert_eq!(200, result.status());
+
+        let result = ureq::post("http://localhost:10104")
+            .header("X-Gitea-Signature", signature)
+            .send("Hello, World!")?;
+        assert_eq!(200, result.status());
+
+        let result = ureq::post("http://localhost:10104")
+            .header("X-Gitlab-Token", "It's a Secret to Everybody")
+            .send("Hello, World!")?;
+        assert_eq!(200, result.status());
+
+        for _ in 0..3 {
+            let context = rx.recv()?.unwrap().context;
+            assert_eq!(TRIGGER_NAME, context.get("TRIGGER_NAME").unwrap());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_parse_push_events_on_the_branch() -> Result<(), Box<dyn Error>> {
+        let mut trigger = HttpTrigger::new(String::from("0.0.0.0:10105"));
+        trigger.set_branch(String::from("refs/heads/main"));
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        thread::spawn(move || {
+            let _ = trigger.listen_inner(tx);
+        });
+
+        // Sleep for the HTTP server to start up.
+        sleep(Duration::from_millis(100));
+
+        let result = ureq::post("http://localhost:10105")
+            .header("X-GitHub-Event", "push")
+            .send(r#"{"ref":"refs/heads/main","after":"abc123","pusher":{"name":"octocat"},"repository":{"full_name":"octocat/hello"}}"#)?;
+        assert_eq!(200, result.status());
+        assert_eq!("OK", result.into_body().read_to_string()?);
+
+        let context = rx.recv()?.unwrap().context;
+        assert_eq!("refs/heads/main", context.get("HTTP_REF").unwrap());
+        assert_eq!("abc123", context.get("HTTP_COMMIT_SHA").unwrap());
+        assert_eq!("octocat", context.get("HTTP_PUSHER").unwrap());
+        assert_eq!(
+            "octocat/hello",
+            context.get("HTTP_REPOSITORY_NAME").unwrap()
+        );
+
+        let result = ureq::post("http://localhost:10105")
+            .header("X-Gitlab-Event", "Push Hook")
+            .send(r#"{"ref":"refs/heads/main","after":"def456","user_username":"tanuki","project":{"path_with_namespace":"tanuki/hello"}}"#)?;
+        assert_eq!(200, result.status());
+
+        let context = rx.recv()?.unwrap().context;
+        assert_eq!("def456", context.get("HTTP_COMMIT_SHA").unwrap());
+        assert_eq!("tanuki", context.get("HTTP_PUSHER").unwrap());
+        assert_eq!("tanuki/hello", context.get("HTTP_REPOSITORY_NAME").unwrap());
+
+        let result = ureq::post("http://localhost:10105")
+            .header("X-Event-Key", "repo:push")
+            .send(r#"{"push":{"changes":[{"new":{"type":"branch","name":"feature","target":{"hash":"000000"}}},{"new":{"type":"branch","name":"main","target":{"hash":"fed789"}}}]},"actor":{"nickname":"bucket"},"repository":{"full_name":"bucket/hello"}}"#)?;
+        assert_eq!(200, result.status());
+
+        let context = rx.recv()?.unwrap().context;
+        assert_eq!("refs/heads/main", context.get("HTTP_REF").unwrap());
+        assert_eq!("fed789", context.get("HTTP_COMMIT_SHA").unwrap());
+        assert_eq!("bucket", context.get("HTTP_PUSHER").unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_ignore_other_branches_and_events() -> Result<(), Box<dyn Error>> {
+        let mut trigger = HttpTrigger::new(String::from("0.0.0.0:10106"));
+        trigger.set_branch(String::from("refs/heads/main"));
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        thread::spawn(move || {
+            let _ = trigger.listen_inner(tx);
+        });
+
+        // Sleep for the HTTP server to start up.
+        sleep(Duration::from_millis(100));
+
+        let result = ureq::post("http://localhost:10106")
+            .header("X-GitHub-Event", "ping")
+            .send(r#"{"zen":"Keep it logically awesome."}"#)?;
+        assert_eq!(200, result.status());
+        assert_eq!("Ignored", result.into_body().read_to_string()?);
+
+        let result = ureq::post("http://localhost:10106")
+            .header("X-Gitea-Event", "push")
+            .send(r#"{"ref":"refs/heads/feature","after":"abc123"}"#)?;
+        assert_eq!(200, result.status());
+        assert_eq!("Ignored", result.into_body().read_to_string()?);
+
+        let result = ureq::post("http://localhost:10106")
+            .header("X-GitHub-Event", "push")
+            .send(r#"{"ref":"refs/tags/v1.0.0","after":"abc123"}"#)?;
+        assert_eq!(200, result.status());
+        assert_eq!("Ignored", result.into_body().read_to_string()?);
+
+        let result = ureq::post("http://localhost:10106")
+            .header("X-GitHub-Event", "push")
+            .send("not json");
+        assert!(
+            matches!(result, Err(ureq::Error::StatusCode(400))),
+            "{result:?} should be 400"
+        );
+
+        // Nothing should be sent to the channel
+        assert!(rx.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_only_trigger_on_allowed_paths_and_methods() -> Result<(), Box<dyn Error>> {
+        let mut trigger = HttpTrigger::new(String::from("0.0.0.0:10107"));
+        trigger.set_paths(vec![String::from("/hooks/deploy")]);
+        trigger.set_methods(vec![String::from("POST")]);
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        thread::spawn(move || {
+            let _ = trigger.listen_inner(tx);
+        });
+
+        // Sleep for the HTTP server to start up.
+        sleep(Duration::from_millis(100));
+
+        let result = ureq::get("http://localhost:10107/favicon.ico").call();
+        assert!(
+            matches!(result, Err(ureq::Error::StatusCode(404))),
+            "{result:?} should be 404"
+        );
+
+        let result = ureq::post("http://localhost:10107/").send_empty();
+        assert!(
+            matches!(result, Err(ureq::Error::StatusCode(404))),
+            "{result:?} should be 404"
+        );
+
+        let result = ureq::get("http://localhost:10107/hooks/deploy").call();
+        assert!(
+            matches!(result, Err(ureq::Error::StatusCode(405))),
+            "{result:?} should be 405"
+        );
+
+        // Nothing should be sent to the channel
+        assert!(rx.try_recv().is_err());
+
+        let result = ureq::post("http://localhost:10107/hooks/deploy?source=ci").send_empty()?;
+        assert_eq!(200, result.status());
+
+        let context = rx.recv()?.unwrap().context;
+        assert_eq!("POST", context.get("HTTP_METHOD").unwrap());
+        assert_eq!("/hooks/deploy?source=ci", context.get("HTTP_URL").unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_wait_for_the_report_in_sync_mode() -> Result<(), Box<dyn Error>> {
+        let mut trigger = HttpTrigger::new(String::from("0.0.0.0:10110"));
+        trigger.set_sync(true);
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        thread::spawn(move || {
+            let _ = trigger.listen_inner(tx);
+        });
+
+        // Answer the requests with a successful and a failed report
+        thread::spawn(move || {
+            let msg = rx.recv().unwrap().unwrap();
+            let _ = msg.reply.unwrap().send(RunReport {
+                updated: true,
+                commit_sha: Some(String::from("abc123")),
+                actions: vec![ActionReport {
+                    name: String::from("echo"),
+                    skipped: false,
+                    exit_code: Some(0),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            });
+            let msg = rx.recv().unwrap().unwrap();
+            let _ = msg.reply.unwrap().send(RunReport {
+                updated: true,
+                commit_sha: Some(String::from("def456")),
+                actions: vec![ActionReport {
+                    name: String::from("false"),
+                    skipped: false,
+                    exit_code: Some(1),
+                    error: Some(String::from("the script returned non-zero exit code 1")),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            });
+        });
+
+        // Sleep for the HTTP server to start up.
+        sleep(Duration::from_millis(100));
+
+        let result = ureq::post("http://localhost:10110").send_empty()?;
+        assert_eq!(200, result.status());
+        let body: serde_json::Value = serde_json::from_str(&result.into_body().read_to_string()?)?;
+        assert_eq!(true, body["success"]);
+        assert_eq!(true, body["updated"]);
+        assert_eq!("abc123", body["commit_sha"]);
+        assert_eq!("echo", body["actions"][0]["name"]);
+        assert_eq!(true, body["actions"][0]["success"]);
+
+        let result = ureq::post("http://localhost:10110").send_empty();
+        assert!(
+            matches!(result, Err(ureq::Error::StatusCode(500))),
+            "{result:?} should be 500"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn create_certificate(directory: &str) -> Result<(String, String), Box<dyn Error>> {
+        let certificate = format!("{directory}/cert.pem");
+        let private_key = format!("{directory}/key.pem");
+        duct::cmd!(
+            "openssl",
+            "req",
+            "-x509",
+            "-newkey",
+            "rsa:2048",
+            "-nodes",
+            "-days",
+            "1",
+            "-subj",
+            "/CN=localhost",
+            "-keyout",
+            &private_key,
+            "-out",
+            &certificate
+        )
+        .stderr_null()
+        .read()?;
+
+        Ok((certificate, private_key))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn create_https_agent() -> ureq::Agent {
+        use ureq::tls::{TlsConfig, TlsProvider};
+
+        ureq::Agent::config_builder()
+            .tls_config(
+                TlsConfig::builder()
+                    .provider(TlsProvider::NativeTls)
+                    .disable_verification(true)
+                    .build(),
+            )
+            .build()
+            .new_agent()
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn it_should_serve_https_and_reload_the_certificate() -> Result<(), Box<dyn Error>> {
+        use rand::distr::{Alphanumeric, SampleString};
+        use std::fs;
+
+        let id = Alphanumeric.sample_string(&mut rand::rng(), 16);
+        let directory = format!("test_directories/{id}");
+        fs::create_dir(&directory)?;
+        let (certificate, private_key) = create_certificate(&directory)?;
+
+        let mut trigger = HttpTrigger::new(String::from("0.0.0.0:10108"));
+        trigger.set_tls(certificate, private_key);
+        trigger.tls_reload_interval = Duration::from_millis(100);
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        thread::spawn(move || {
+            let _ = trigger.listen_inner(tx);
+        });
+
+        // Sleep for the HTTP server to start up.
+        sleep(Duration::from_millis(200));
+
+        let result = create_https_agent().get("https://localhost:10108").call()?;
+        assert_eq!(200, result.status());
+        assert_eq!("OK", result.into_body().read_to_string()?);
+        assert!(rx.recv()?.is_some());
+
+        // Renew the certificate and wait for the server to restart.
+        sleep(Duration::from_millis(10));
+        create_certificate(&directory)?;
+        sleep(Duration::from_millis(500));
+
+        // Create a new agent, because the kept-alive connections are not restarted.
+        let result = create_https_agent().get("https://localhost:10108").call()?;
+        assert_eq!(200, result.status());
+        assert!(rx.recv()?.is_some());
+
+        // A half-written certificate should keep the server running with the old one.
+        let (certificate, _) = create_certificate(&directory)?;
+        fs::write(&certificate, "-----BEGIN CERTIFICATE-----\n")?;
+        sleep(Duration::from_millis(500));
+
+        let result = create_https_agent().get("https://localhost:10108").call()?;
+        assert_eq!(200, result.status());
+        assert!(rx.recv()?.is_some());
+
+        // It should be reloaded, when the certificate is written completely.
+        create_certificate(&directory)?;
+        sleep(Duration::from_millis(500));
+
+        let result = create_https_agent().get("https://localhost:10108").call()?;
+        assert_eq!(200, result.status());
+        assert!(rx.recv()?.is_some());
+
+        fs::remove_dir_all(&directory)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fail_if_certificate_is_missing() {
+        let mut trigger = HttpTrigger::new(String::from("0.0.0.0:10109"));
+        trigger.set_tls(
+            String::from("/path/to/nowhere/cert.pem"),
+            String::from("/path/to/nowhere/key.pem"),
+        );
+        let (tx, _rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        let result = trigger.listen_inner(tx);
+        assert!(
+            matches!(result, Err(HttpError::CantLoadCertificate(_, _))),
+            "{result:?} should be CantLoadCertificate"
+        );
+    }
+
     #[test]
     fn it_should_fail_if_http_url_invalid() {
         let trigger = HttpTrigger::new(String::from("aaaaa"));
 
-        let (tx, _rx) = mpsc::channel::<Option<Context>>();
+        let (tx, _rx) = mpsc::channel::<Option<TriggerMessage>>();
 
         let result = trigger.listen_inner(tx);
         assert!(
@@ -146,7 +856,7 @@ mod tests {
     #[test]
     fn it_should_fail_if_sending_fails() -> Result<(), Box<dyn Error>> {
         let trigger = HttpTrigger::new(String::from("0.0.0.0:10102"));
-        let (tx, rx) = mpsc::channel::<Option<Context>>();
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
 
         thread::spawn(move || {
             // Sleep for the HTTP server to start up.
@@ -0,0 +1,122 @@
+use super::find_header;
+use serde_json::Value;
+use tiny_http::Header;
+
+/// A push to a branch or a tag, parsed from the webhook payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushEvent {
+    pub git_ref: String,
+    pub after: String,
+    pub pusher: String,
+    pub repository: String,
+}
+
+/// The events that the git servers send in webhooks.
+#[derive(Debug, PartialEq, Eq)]
+pub enum WebhookEvent {
+    /// A push event, with one or more updated refs.
+    Push(Vec<PushEvent>),
+    /// Any other event, e.g. ping, issues or merge requests.
+    Other(String),
+}
+
+type PushParser = fn(&[u8]) -> Option<Vec<PushEvent>>;
+
+fn get_string(value: &Value, pointers: &[&str]) -> String {
+    pointers
+        .iter()
+        .find_map(|pointer| value.pointer(pointer).and_then(Value::as_str))
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn parse_json(body: &[u8]) -> Option<Value> {
+    serde_json::from_slice(body).ok()
+}
+
+// GitHub, Gitea and Forgejo share the same payload structure.
+fn parse_github_push(body: &[u8]) -> Option<Vec<PushEvent>> {
+    let value = parse_json(body)?;
+    Some(vec![PushEvent {
+        git_ref: value.get("ref")?.as_str()?.to_string(),
+        after: get_string(&value, &["/after"]),
+        pusher: get_string(
+            &value,
+            &["/pusher/name", "/pusher/username", "/pusher/login"],
+        ),
+        repository: get_string(&value, &["/repository/full_name", "/repository/name"]),
+    }])
+}
+
+fn parse_gitlab_push(body: &[u8]) -> Option<Vec<PushEvent>> {
+    let value = parse_json(body)?;
+    Some(vec![PushEvent {
+        git_ref: value.get("ref")?.as_str()?.to_string(),
+        after: get_string(&value, &["/after", "/checkout_sha"]),
+        pusher: get_string(&value, &["/user_username", "/user_name"]),
+        repository: get_string(
+            &value,
+            &["/project/path_with_namespace", "/repository/name"],
+        ),
+    }])
+}
+
+fn parse_bitbucket_push(body: &[u8]) -> Option<Vec<PushEvent>> {
+    let value = parse_json(body)?;
+    let pusher = get_string(&value, &["/actor/nickname", "/actor/display_name"]);
+    let repository = get_string(&value, &["/repository/full_name", "/repository/name"]);
+
+    // Deleted refs don't have a new state, these can be skipped
+    let pushes = value
+        .pointer("/push/changes")?
+        .as_array()?
+        .iter()
+        .filter_map(|change| {
+            let new = change.get("new")?;
+            let prefix = match new.get("type")?.as_str()? {
+                "branch" => "refs/heads",
+                "tag" => "refs/tags",
+                _ => return None,
+            };
+            Some(PushEvent {
+                git_ref: format!("{prefix}/{}", new.get("name")?.as_str()?),
+                after: get_string(new, &["/target/hash"]),
+                pusher: pusher.clone(),
+                repository: repository.clone(),
+            })
+        })
+        .collect();
+
+    Some(pushes)
+}
+
+/// Parse the webhook event from the headers and the body of the request.
+///
+/// Returns `None` if the request is not coming from a known git server,
+/// and `Some(Err(event))` if it is a push event, but the body cannot be parsed.
+pub fn parse_webhook(headers: &[Header], body: &[u8]) -> Option<Result<WebhookEvent, String>> {
+    // Gitea and Forgejo also send the GitHub header, so these have to be checked first
+    let (event, is_push, parse): (_, _, PushParser) = if let Some(event) =
+        find_header(headers, "X-Gitea-Event").or_else(|| find_header(headers, "X-Forgejo-Event"))
+    {
+        (event, event == "push", parse_github_push)
+    } else if let Some(event) = find_header(headers, "X-GitHub-Event") {
+        (event, event == "push", parse_github_push)
+    } else if let Some(event) = find_header(headers, "X-Gitlab-Event") {
+        (
+            event,
+            event == "Push Hook" || event == "Tag Push Hook",
+            parse_gitlab_push,
+        )
+    } else if let Some(event) = find_header(headers, "X-Event-Key") {
+        (event, event == "repo:push", parse_bitbucket_push)
+    } else {
+        return None;
+    };
+
+    if is_push {
+        Some(parse(body).map(WebhookEvent::Push).ok_or(event.to_string()))
+    } else {
+        Some(Ok(WebhookEvent::Other(event.to_string())))
+    }
+}
@@ -0,0 +1,54 @@
+use super::find_header;
+use hmac::{Hmac, KeyInit, Mac};
+use log::trace;
+use sha2::Sha256;
+use tiny_http::Header;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The signature schemes of the different git servers.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignatureScheme {
+    /// GitHub sends a `sha256=`-prefixed HMAC-SHA256 of the body in `X-Hub-Signature-256`.
+    GitHub,
+    /// GitLab sends the secret as a plain token in `X-Gitlab-Token`.
+    GitLab,
+    /// Gitea and Forgejo send a HMAC-SHA256 of the body in `X-Gitea-Signature`.
+    Gitea,
+}
+
+/// Compare two byte slices in constant time, to avoid leaking the secret with timing attacks.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn verify_hmac(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature.trim()) else {
+        return false;
+    };
+    let Ok(mut mac) = HmacSha256::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Verify the request with the shared secret, returning the scheme that matched.
+///
+/// Returns `None` if the request is not signed or the signature is invalid.
+pub fn verify_signature(secret: &str, headers: &[Header], body: &[u8]) -> Option<SignatureScheme> {
+    if let Some(signature) = find_header(headers, "X-Hub-Signature-256") {
+        trace!("Verifying GitHub signature.");
+        let signature = signature.strip_prefix("sha256=")?;
+        verify_hmac(secret, body, signature).then_some(SignatureScheme::GitHub)
+    } else if let Some(signature) = find_header(headers, "X-Gitea-Signature") {
+        trace!("Verifying Gitea signature.");
+        verify_hmac(secret, body, signature).then_some(SignatureScheme::Gitea)
+    } else if let Some(token) = find_header(headers, "X-Gitlab-Token") {
+        trace!("Verifying GitLab token.");
+        constant_time_eq(secret.as_bytes(), token.as_bytes()).then_some(SignatureScheme::GitLab)
+    } else {
+        trace!("There is no signature on the request.");
+        None
+    }
+}
@@ -0,0 +1,92 @@
+use super::HttpError;
+use log::{debug, trace};
+use std::{
+    fs,
+    path::PathBuf,
+    thread::sleep,
+    time::{Duration, SystemTime},
+};
+use tiny_http::Server;
+
+/// The certificate and private key files in PEM format to serve HTTPS.
+///
+/// The files are watched for changes, so renewed certificates (e.g. with certbot)
+/// can be reloaded without restarting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsFiles {
+    certificate: PathBuf,
+    private_key: PathBuf,
+}
+
+/// The contents of the certificate and private key files, that the server was started with.
+#[derive(Clone)]
+pub struct TlsCertificate {
+    certificate: Vec<u8>,
+    private_key: Vec<u8>,
+}
+
+impl TlsFiles {
+    pub fn new(certificate: PathBuf, private_key: PathBuf) -> Self {
+        Self {
+            certificate,
+            private_key,
+        }
+    }
+
+    /// Get the last modification of the files, to know if it should be reloaded.
+    pub fn modified(&self) -> Option<(SystemTime, SystemTime)> {
+        let certificate = fs::metadata(&self.certificate).and_then(|m| m.modified());
+        let private_key = fs::metadata(&self.private_key).and_then(|m| m.modified());
+
+        certificate.ok().zip(private_key.ok())
+    }
+
+    /// Read the certificate and the private key from the files.
+    pub fn load(&self) -> Result<TlsCertificate, HttpError> {
+        let certificate = fs::read(&self.certificate).map_err(|err| {
+            HttpError::CantLoadCertificate(self.certificate.to_string_lossy().to_string(), err)
+        })?;
+        let private_key = fs::read(&self.private_key).map_err(|err| {
+            HttpError::CantLoadCertificate(self.private_key.to_string_lossy().to_string(), err)
+        })?;
+
+        Ok(TlsCertificate {
+            certificate,
+            private_key,
+        })
+    }
+}
+
+impl TlsCertificate {
+    fn to_config(&self) -> tiny_http::SslConfig {
+        tiny_http::SslConfig {
+            certificate: self.certificate.clone(),
+            private_key: self.private_key.clone(),
+        }
+    }
+
+    /// Check that the certificate and the private key can be used (e.g. they are not half-written
+    /// or mismatched), by starting a server on a random local port.
+    pub fn validate(&self) -> Result<(), HttpError> {
+        Server::https("127.0.0.1:0", self.to_config())
+            .map(drop)
+            .map_err(|err| HttpError::InvalidCertificate(err.to_string()))
+    }
+
+    /// Start an HTTPS server with the certificate and private key.
+    pub fn start_server(&self, http: &str) -> Result<Server, HttpError> {
+        // The previous server might hold the port for a little while after dropping it
+        for _ in 0..10 {
+            match Server::https(http, self.to_config()) {
+                Ok(server) => return Ok(server),
+                Err(err) => {
+                    trace!("Failed starting HTTPS server: {err}.");
+                    sleep(Duration::from_millis(100));
+                }
+            }
+        }
+
+        debug!("Failed starting HTTPS server, the port is used or the certificate is invalid.");
+        Err(HttpError::CantStartServer(http.to_string()))
+    }
+}
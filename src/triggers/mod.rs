@@ -1,8 +1,10 @@
-use crate::context::Context;
+use crate::{context::Context, start::RunReport};
 use mockall::automock;
 use std::sync::mpsc::Sender;
 use thiserror::Error;
 
+/// A trigger that runs when a file or directory changes.
+pub mod file;
 /// A trigger that runs on an HTTP request.
 pub mod http;
 /// A trigger that runs the checks once and then exits.
@@ -11,6 +13,8 @@ pub mod once;
 pub mod schedule;
 /// A trigger that runs the checks periodically.
 pub mod signal;
+/// A trigger that runs the checks when a deploy window opens.
+pub mod window;
 
 /// A custom error for describing the error cases for triggers
 #[derive(Debug, Error)]
@@ -20,12 +24,30 @@ pub enum TriggerError {
     Misconfigured(String),
     /// Cannot send trigger with Sender. This usually because the receiver is dropped.
     #[error("cannot trigger changes, receiver hang up")]
-    ReceiverHangup(#[from] std::sync::mpsc::SendError<Option<Context>>),
+    ReceiverHangup(#[from] std::sync::mpsc::SendError<Option<TriggerMessage>>),
     /// Running the trigger failed.
     #[error("{0}")]
     FailedTrigger(String),
 }
 
+/// A message sent by a trigger to initiate a check.
+///
+/// The trigger can add a reply channel, if it wants to wait for the result of the check and the actions.
+#[derive(Debug)]
+pub struct TriggerMessage {
+    pub context: Context,
+    pub reply: Option<Sender<RunReport>>,
+}
+
+impl From<Context> for TriggerMessage {
+    fn from(context: Context) -> Self {
+        Self {
+            context,
+            reply: None,
+        }
+    }
+}
+
 /// A trigger is a long running background process, which initiates the checks.
 ///
 /// Triggers may include:
@@ -35,5 +57,5 @@ pub enum TriggerError {
 #[automock]
 pub trait Trigger: Sync + Send {
     /// Start the trigger process.
-    fn listen(&self, tx: Sender<Option<Context>>) -> Result<(), TriggerError>;
+    fn listen(&self, tx: Sender<Option<TriggerMessage>>) -> Result<(), TriggerError>;
 }
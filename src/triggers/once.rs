@@ -1,4 +1,4 @@
-use super::{Trigger, TriggerError};
+use super::{Trigger, TriggerError, TriggerMessage};
 use crate::context::Context;
 use log::debug;
 use std::{collections::HashMap, sync::mpsc::Sender};
@@ -10,10 +10,10 @@ pub struct OnceTrigger;
 
 impl Trigger for OnceTrigger {
     /// Starts a trigger that runs once and terminates after.
-    fn listen(&self, tx: Sender<Option<Context>>) -> Result<(), TriggerError> {
+    fn listen(&self, tx: Sender<Option<TriggerMessage>>) -> Result<(), TriggerError> {
         debug!("Triggering only once.");
         let context: Context = HashMap::from([("TRIGGER_NAME", TRIGGER_NAME.to_string())]);
-        tx.send(Some(context))?;
+        tx.send(Some(context.into()))?;
         tx.send(None)?;
         Ok(())
     }
@@ -27,11 +27,11 @@ mod tests {
     #[test]
     fn it_should_trigger_once_and_stop() {
         let trigger = OnceTrigger;
-        let (tx, rx) = mpsc::channel::<Option<Context>>();
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
 
         trigger.listen(tx).unwrap();
 
-        let msgs: Vec<_> = rx.iter().collect();
+        let msgs: Vec<_> = rx.iter().map(|msg| msg.map(|m| m.context)).collect();
         assert_eq!(
             vec![
                 Some(HashMap::from([("TRIGGER_NAME", TRIGGER_NAME.to_string())])),
@@ -1,9 +1,13 @@
-use super::{Trigger, TriggerError};
+use super::{Trigger, TriggerError, TriggerMessage};
 use crate::context::Context;
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
 use duration_string::DurationString;
-use log::info;
+use log::{debug, info};
 use std::{
     collections::HashMap,
+    str::FromStr,
     sync::mpsc::Sender,
     thread::sleep,
     time::{Duration, Instant},
@@ -12,25 +16,94 @@ use thiserror::Error;
 
 const TRIGGER_NAME: &str = "SCHEDULE";
 
+/// A cron expression, that is evaluated in the given timezone.
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    expression: String,
+    cron: Cron,
+    timezone: Option<Tz>,
+}
+
+impl CronSchedule {
+    /// Parse a cron expression (with optional seconds) and an optional timezone (e.g. "Europe/Berlin").
+    ///
+    /// If there is no timezone given, the expression is evaluated in the local timezone.
+    pub fn new(expression: &str, timezone: Option<&str>) -> Result<Self, ScheduleError> {
+        let cron = Cron::from_str(expression).map_err(|err| {
+            ScheduleError::InvalidCronExpression(expression.to_string(), err.to_string())
+        })?;
+        let timezone = timezone
+            .map(|timezone| {
+                Tz::from_str(timezone)
+                    .map_err(|_| ScheduleError::InvalidTimezone(timezone.to_string()))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            expression: expression.to_string(),
+            cron,
+            timezone,
+        })
+    }
+
+    fn find_next_in<T: TimeZone>(&self, time: DateTime<T>) -> Option<DateTime<FixedOffset>> {
+        self.cron
+            .find_next_occurrence(&time, false)
+            .ok()
+            .map(|next| next.fixed_offset())
+    }
+
+    /// Find the first occurrence of the schedule after the given time.
+    pub fn find_next(&self, time: DateTime<Utc>) -> Option<DateTime<FixedOffset>> {
+        // Cron works on whole seconds, the fraction would be kept in the occurrence
+        let time = time.with_nanosecond(0).unwrap_or(time);
+        match self.timezone {
+            Some(timezone) => self.find_next_in(time.with_timezone(&timezone)),
+            None => self.find_next_in(time.with_timezone(&Local)),
+        }
+    }
+}
+
+/// The schedule on which the trigger runs.
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    /// Trigger immediately, and then every time the duration passes.
+    Interval(Duration),
+    /// Trigger every time the cron expression matches.
+    Cron(Box<CronSchedule>),
+}
+
 /// A trigger that runs the checks periodically.
 ///
 /// This is running in an infinite loop, triggering every time.
 pub struct ScheduleTrigger {
-    duration: Duration,
+    schedule: Schedule,
     timeout: Option<Duration>,
 }
 
 /// Custom error describing the error cases for the ScheduleTrigger.
 #[derive(Debug, Error)]
 pub enum ScheduleError {
+    /// The cron expression cannot be parsed.
+    #[error("cron expression {0} is invalid: {1}")]
+    InvalidCronExpression(String, String),
+    /// The timezone is not in the IANA timezone database.
+    #[error("timezone {0} is invalid, use a name like Europe/Berlin")]
+    InvalidTimezone(String),
+    /// The cron expression will never match again.
+    #[error("cron expression {0} has no next occurrence")]
+    NoNextOccurrence(String),
     /// Cannot send trigger with Sender. This usually because the receiver is dropped.
     #[error("cannot trigger changes, receiver hang up")]
-    ReceiverHangup(#[from] std::sync::mpsc::SendError<Option<Context>>),
+    ReceiverHangup(#[from] std::sync::mpsc::SendError<Option<TriggerMessage>>),
 }
 
 impl From<ScheduleError> for TriggerError {
     fn from(val: ScheduleError) -> Self {
         match val {
+            ScheduleError::InvalidCronExpression(_, _)
+            | ScheduleError::InvalidTimezone(_)
+            | ScheduleError::NoNextOccurrence(_) => TriggerError::Misconfigured(val.to_string()),
             ScheduleError::ReceiverHangup(s) => TriggerError::ReceiverHangup(s),
         }
     }
@@ -40,7 +113,7 @@ impl ScheduleTrigger {
     /// Creates a new ScheduleTrigger with duration.
     pub fn new(duration: Duration) -> Self {
         Self {
-            duration,
+            schedule: Schedule::Interval(duration),
             timeout: None,
         }
     }
@@ -48,7 +121,23 @@ impl ScheduleTrigger {
     /// Creates a new ScheduleTrigger with duration and timeout.
     pub fn new_with_timeout(duration: Duration, timeout: Duration) -> Self {
         Self {
-            duration,
+            schedule: Schedule::Interval(duration),
+            timeout: Some(timeout),
+        }
+    }
+
+    /// Creates a new ScheduleTrigger with a cron expression.
+    pub fn new_cron(cron: CronSchedule) -> Self {
+        Self {
+            schedule: Schedule::Cron(Box::new(cron)),
+            timeout: None,
+        }
+    }
+
+    /// Creates a new ScheduleTrigger with a cron expression and timeout.
+    pub fn new_cron_with_timeout(cron: CronSchedule, timeout: Duration) -> Self {
+        Self {
+            schedule: Schedule::Cron(Box::new(cron)),
             timeout: Some(timeout),
         }
     }
@@ -57,21 +146,32 @@ impl ScheduleTrigger {
     /// returns false in case of an error or a timeout. One step should take exactly the duration.
     /// In case of an error it terminates or if it will reach the final timeout it will
     /// wait until the end of the timeout and returns with false.
+    ///
+    /// With a cron expression the step waits until the next occurrence and triggers after.
     pub fn step(
         &self,
-        tx: Sender<Option<Context>>,
+        tx: Sender<Option<TriggerMessage>>,
+        final_timeout: Option<Instant>,
+    ) -> Result<bool, ScheduleError> {
+        match &self.schedule {
+            Schedule::Interval(duration) => self.step_interval(*duration, tx, final_timeout),
+            Schedule::Cron(cron) => self.step_cron(cron, tx, final_timeout),
+        }
+    }
+
+    fn step_interval(
+        &self,
+        duration: Duration,
+        tx: Sender<Option<TriggerMessage>>,
         final_timeout: Option<Instant>,
     ) -> Result<bool, ScheduleError> {
-        let next_check = Instant::now() + self.duration;
+        let next_check = Instant::now() + duration;
 
         let context: Context = HashMap::from([
             ("TRIGGER_NAME", TRIGGER_NAME.to_string()),
-            (
-                "SCHEDULE_DELAY",
-                DurationString::from(self.duration).to_string(),
-            ),
+            ("SCHEDULE_DELAY", DurationString::from(duration).to_string()),
         ]);
-        tx.send(Some(context))?;
+        tx.send(Some(context.into()))?;
 
         if let Some(final_timeout) = final_timeout {
             if next_check > final_timeout {
@@ -80,7 +180,7 @@ impl ScheduleTrigger {
                 return Ok(false);
             }
         }
-        // TODO: handle overlaps
+        // If the check takes longer than the duration, the overlap policy decides what to do
         let until_next_check = next_check - Instant::now();
         sleep(until_next_check);
 
@@ -91,6 +191,47 @@ impl ScheduleTrigger {
             Ok(true)
         }
     }
+
+    fn step_cron(
+        &self,
+        cron: &CronSchedule,
+        tx: Sender<Option<TriggerMessage>>,
+        final_timeout: Option<Instant>,
+    ) -> Result<bool, ScheduleError> {
+        let next_check = cron
+            .find_next(Utc::now())
+            .ok_or_else(|| ScheduleError::NoNextOccurrence(cron.expression.clone()))?;
+        debug!("Next scheduled check is at {}.", next_check.to_rfc3339());
+
+        let until_next_check = (next_check.to_utc() - Utc::now())
+            .to_std()
+            .unwrap_or_default();
+        if let Some(final_timeout) = final_timeout {
+            if Instant::now() + until_next_check > final_timeout {
+                sleep(final_timeout.saturating_duration_since(Instant::now()));
+                return Ok(false);
+            }
+        }
+
+        // The clock can differ from the monotonic time, so make sure that we don't wake up early
+        sleep(until_next_check);
+        while let Ok(remaining) = (next_check.to_utc() - Utc::now()).to_std() {
+            sleep(remaining);
+        }
+
+        let context: Context = HashMap::from([
+            ("TRIGGER_NAME", TRIGGER_NAME.to_string()),
+            ("SCHEDULE_CRON", cron.expression.clone()),
+            ("SCHEDULE_TIME", next_check.to_rfc3339()),
+        ]);
+        tx.send(Some(context.into()))?;
+
+        if let Some(final_timeout) = final_timeout {
+            Ok(Instant::now() < final_timeout)
+        } else {
+            Ok(true)
+        }
+    }
 }
 
 impl Trigger for ScheduleTrigger {
@@ -98,12 +239,19 @@ impl Trigger for ScheduleTrigger {
     /// Every step triggers and then waits the given duration. In case of an error,
     /// it terminates or if it will reach the final timeout it will wait until
     /// the end of the timeout and return.
-    fn listen(&self, tx: Sender<Option<Context>>) -> Result<(), TriggerError> {
+    fn listen(&self, tx: Sender<Option<TriggerMessage>>) -> Result<(), TriggerError> {
         let final_timeout = self.timeout.map(|t| Instant::now() + t);
-        info!(
-            "Starting schedule in every {}.",
-            DurationString::new(self.duration)
-        );
+        match &self.schedule {
+            Schedule::Interval(duration) => {
+                info!(
+                    "Starting schedule in every {}.",
+                    DurationString::new(*duration)
+                );
+            }
+            Schedule::Cron(cron) => {
+                info!("Starting schedule on cron \"{}\".", cron.expression);
+            }
+        }
 
         loop {
             let should_continue = self.step(tx.clone(), final_timeout)?;
@@ -125,7 +273,9 @@ mod tests {
     #[test]
     fn it_should_be_created_from_duration() {
         let trigger = ScheduleTrigger::new(Duration::from_millis(100));
-        assert_eq!(Duration::from_millis(100), trigger.duration);
+        assert!(
+            matches!(trigger.schedule, Schedule::Interval(d) if d == Duration::from_millis(100))
+        );
         assert_eq!(None, trigger.timeout);
     }
 
@@ -135,14 +285,76 @@ mod tests {
             Duration::from_millis(100),
             Duration::from_millis(200),
         );
-        assert_eq!(Duration::from_millis(100), trigger.duration);
+        assert!(
+            matches!(trigger.schedule, Schedule::Interval(d) if d == Duration::from_millis(100))
+        );
         assert_eq!(Some(Duration::from_millis(200)), trigger.timeout);
     }
 
+    #[test]
+    fn it_should_be_created_from_cron_and_timezone() -> Result<(), ScheduleError> {
+        let cron = CronSchedule::new("*/5 9-17 * * 1-5", Some("Europe/Berlin"))?;
+        let trigger = ScheduleTrigger::new_cron(cron);
+        assert!(
+            matches!(&trigger.schedule, Schedule::Cron(c) if c.expression == "*/5 9-17 * * 1-5" && c.timezone == Some(chrono_tz::Europe::Berlin))
+        );
+        assert_eq!(None, trigger.timeout);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fail_on_invalid_cron_or_timezone() {
+        let result = CronSchedule::new("* * *", None);
+        assert!(
+            matches!(result, Err(ScheduleError::InvalidCronExpression(_, _))),
+            "{result:?} should be InvalidCronExpression"
+        );
+
+        let result = CronSchedule::new("0 3 * * *", Some("Mars/Olympus_Mons"));
+        assert!(
+            matches!(result, Err(ScheduleError::InvalidTimezone(_))),
+            "{result:?} should be InvalidTimezone"
+        );
+    }
+
+    #[test]
+    fn it_should_find_the_next_occurrence_in_the_timezone() -> Result<(), ScheduleError> {
+        let cron = CronSchedule::new("0 3 * * *", Some("Europe/Budapest"))?;
+
+        // Budapest is UTC+1 in the winter and UTC+2 in the summer
+        let winter = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+        let next = cron.find_next(winter).unwrap();
+        assert_eq!("2026-01-16T03:00:00+01:00", next.to_rfc3339());
+        assert_eq!(Utc.with_ymd_and_hms(2026, 1, 16, 2, 0, 0).unwrap(), next);
+
+        let summer = Utc.with_ymd_and_hms(2026, 7, 15, 12, 0, 0).unwrap();
+        let next = cron.find_next(summer).unwrap();
+        assert_eq!("2026-07-16T03:00:00+02:00", next.to_rfc3339());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_find_the_next_occurrence_on_weekdays() -> Result<(), ScheduleError> {
+        let cron = CronSchedule::new("*/5 9-17 * * 1-5", Some("UTC"))?;
+
+        // 2026-10-16 is a Friday, so after business hours it should skip to Monday
+        let friday_evening = Utc.with_ymd_and_hms(2026, 10, 16, 18, 0, 0).unwrap();
+        let next = cron.find_next(friday_evening).unwrap();
+        assert_eq!(Utc.with_ymd_and_hms(2026, 10, 19, 9, 0, 0).unwrap(), next);
+
+        let monday_morning = Utc.with_ymd_and_hms(2026, 10, 19, 9, 2, 0).unwrap();
+        let next = cron.find_next(monday_morning).unwrap();
+        assert_eq!(Utc.with_ymd_and_hms(2026, 10, 19, 9, 5, 0).unwrap(), next);
+
+        Ok(())
+    }
+
     #[test]
     fn it_should_trigger_every_100_ms() -> Result<(), TriggerError> {
         let trigger = ScheduleTrigger::new(Duration::from_millis(100));
-        let (tx, rx) = mpsc::channel::<Option<Context>>();
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
 
         for _ in 0..5 {
             let start = Instant::now();
@@ -160,7 +372,7 @@ mod tests {
             );
 
             // It should contain the hashmap
-            let context = msg.unwrap();
+            let context = msg.unwrap().context;
             assert_eq!(TRIGGER_NAME, context.get("TRIGGER_NAME").unwrap());
             assert_eq!("100ms", context.get("SCHEDULE_DELAY").unwrap());
         }
@@ -171,7 +383,7 @@ mod tests {
     #[test]
     fn it_should_not_continue_after_the_timeout() -> Result<(), TriggerError> {
         let trigger = ScheduleTrigger::new(Duration::from_millis(100));
-        let (tx, _rx) = mpsc::channel::<Option<Context>>();
+        let (tx, _rx) = mpsc::channel::<Option<TriggerMessage>>();
 
         let start = Instant::now();
         let final_timeout = start + Duration::from_millis(350);
@@ -197,10 +409,55 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn it_should_trigger_on_cron_every_second() -> Result<(), TriggerError> {
+        let cron = CronSchedule::new("* * * * * *", None)?;
+        let trigger = ScheduleTrigger::new_cron(cron);
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        let mut last_time = None;
+        for _ in 0..3 {
+            let should_continue = trigger.step(tx.clone(), None)?;
+            assert!(should_continue);
+
+            // It should trigger on the whole seconds
+            let context = rx.recv().unwrap().unwrap().context;
+            assert_eq!(TRIGGER_NAME, context.get("TRIGGER_NAME").unwrap());
+            assert_eq!("* * * * * *", context.get("SCHEDULE_CRON").unwrap());
+
+            let time = DateTime::parse_from_rfc3339(context.get("SCHEDULE_TIME").unwrap()).unwrap();
+            assert!(Utc::now() >= time, "It shouldn't trigger before {time}.");
+            if let Some(last_time) = last_time {
+                assert_eq!(chrono::Duration::seconds(1), time - last_time);
+            }
+            last_time = Some(time);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_not_trigger_cron_after_the_timeout() -> Result<(), TriggerError> {
+        let cron = CronSchedule::new("0 0 1 1 *", None)?;
+        let trigger = ScheduleTrigger::new_cron(cron);
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        let start = Instant::now();
+        let final_timeout = start + Duration::from_millis(100);
+        let should_continue = trigger.step(tx.clone(), Some(final_timeout))?;
+
+        // It should wait until the timeout and stop without triggering
+        assert!(!should_continue);
+        assert!(start.elapsed() >= Duration::from_millis(95));
+        assert!(rx.try_recv().is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn it_should_not_trigger_on_a_send_error() {
         let trigger = ScheduleTrigger::new(Duration::from_millis(100));
-        let (tx, rx) = mpsc::channel::<Option<Context>>();
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
 
         // Close receiving end, to create a send error
         drop(rx);
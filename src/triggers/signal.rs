@@ -1,14 +1,16 @@
-use super::{Trigger, TriggerError};
-use crate::context::Context;
-use std::sync::mpsc::Sender;
+use super::{Trigger, TriggerError, TriggerMessage};
 use log::debug;
+use std::sync::mpsc::Sender;
 
 #[cfg(unix)]
 use std::sync::atomic::AtomicU8;
 
-const _TRIGGER_NAME: &str = "SIGNAL";
+#[cfg(unix)]
+const TRIGGER_NAME: &str = "SIGNAL";
 
 /// A trigger that terminates the program on a signal.
+///
+/// On SIGHUP and SIGUSR1 it runs the checks immediately instead.
 pub struct SignalTrigger {
     #[cfg(unix)]
     trigger_count: AtomicU8,
@@ -23,13 +25,35 @@ impl SignalTrigger {
     }
 
     #[cfg(unix)]
-    fn listen_inner<I>(&self, tx: Sender<Option<Context>>, signals: I) -> Result<(), TriggerError>
+    fn listen_inner<I>(
+        &self,
+        tx: Sender<Option<TriggerMessage>>,
+        signals: I,
+    ) -> Result<(), TriggerError>
     where
         I: IntoIterator<Item = i32>,
     {
-        use log::error;
-        use std::{process, sync::atomic::Ordering, thread::sleep, time::Duration};
+        use crate::context::Context;
+        use log::{error, info};
+        use nix::sys::signal::Signal;
+        use signal_hook::consts::{SIGHUP, SIGUSR1};
+        use std::{
+            collections::HashMap, process, sync::atomic::Ordering, thread::sleep, time::Duration,
+        };
         for signal in signals.into_iter() {
+            if signal == SIGHUP || signal == SIGUSR1 {
+                let signal_name = Signal::try_from(signal)
+                    .map(|s| s.as_str().to_string())
+                    .unwrap_or_else(|_| signal.to_string());
+                info!("Got signal {signal_name}, checking for updates.");
+                let context: Context = HashMap::from([
+                    ("TRIGGER_NAME", TRIGGER_NAME.to_string()),
+                    ("SIGNAL_NAME", signal_name),
+                ]);
+                tx.send(Some(context.into()))?;
+                continue;
+            }
+
             let previous = self.trigger_count.fetch_add(1, Ordering::Acquire);
             if previous == 0 {
                 debug!("Got signal {signal}, terminating after all actions finished.",);
@@ -55,15 +79,16 @@ impl Default for SignalTrigger {
 }
 
 impl Trigger for SignalTrigger {
-    /// Starts a trigger that iterates over signals and terminates the program.
+    /// Starts a trigger that iterates over signals and terminates the program,
+    /// or triggers the checks on SIGHUP and SIGUSR1.
     #[cfg(unix)]
-    fn listen(&self, tx: Sender<Option<Context>>) -> Result<(), TriggerError> {
+    fn listen(&self, tx: Sender<Option<TriggerMessage>>) -> Result<(), TriggerError> {
         use log::warn;
         use signal_hook::{
-            consts::TERM_SIGNALS,
+            consts::{SIGHUP, SIGUSR1, TERM_SIGNALS},
             iterator::{exfiltrator::SignalOnly, SignalsInfo},
         };
-        let signals = SignalsInfo::<SignalOnly>::new(TERM_SIGNALS);
+        let signals = SignalsInfo::<SignalOnly>::new(TERM_SIGNALS.iter().chain(&[SIGHUP, SIGUSR1]));
         if let Ok(mut signals) = signals {
             self.listen_inner(tx, &mut signals)?;
         } else {
@@ -74,7 +99,7 @@ impl Trigger for SignalTrigger {
     }
 
     #[cfg(not(unix))]
-    fn listen(&self, _tx: Sender<Option<Context>>) -> Result<(), TriggerError> {
+    fn listen(&self, _tx: Sender<Option<TriggerMessage>>) -> Result<(), TriggerError> {
         debug!("Signal handlers are not supported on non-unix systems.");
 
         Ok(())
@@ -90,13 +115,42 @@ mod tests {
     #[test]
     fn it_should_trigger_on_the_first_signal() {
         let trigger = SignalTrigger::new();
-        let (tx, rx) = mpsc::channel::<Option<Context>>();
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
 
         let signals = vec![9];
 
         trigger.listen_inner(tx, signals).unwrap();
 
-        let msgs: Vec<_> = rx.iter().collect();
+        let msgs: Vec<_> = rx.iter().map(|msg| msg.map(|m| m.context)).collect();
         assert_eq!(vec![None], msgs);
     }
+
+    #[test]
+    fn it_should_trigger_checks_on_sighup_and_sigusr1() {
+        use signal_hook::consts::{SIGHUP, SIGTERM, SIGUSR1};
+        use std::collections::HashMap;
+
+        let trigger = SignalTrigger::new();
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        let signals = vec![SIGUSR1, SIGHUP, SIGTERM];
+
+        trigger.listen_inner(tx, signals).unwrap();
+
+        let msgs: Vec<_> = rx.iter().map(|msg| msg.map(|m| m.context)).collect();
+        assert_eq!(
+            vec![
+                Some(HashMap::from([
+                    ("TRIGGER_NAME", TRIGGER_NAME.to_string()),
+                    ("SIGNAL_NAME", String::from("SIGUSR1"))
+                ])),
+                Some(HashMap::from([
+                    ("TRIGGER_NAME", TRIGGER_NAME.to_string()),
+                    ("SIGNAL_NAME", String::from("SIGHUP"))
+                ])),
+                None
+            ],
+            msgs
+        );
+    }
 }
@@ -0,0 +1,61 @@
+use super::{Trigger, TriggerError, TriggerMessage};
+use crate::{checks::git::DeployWindows, context::Context};
+use chrono::Utc;
+use log::{debug, info};
+use std::{collections::HashMap, sync::mpsc::Sender, thread::sleep};
+
+const TRIGGER_NAME: &str = "WINDOW";
+
+/// A trigger that runs the checks when a deploy window opens.
+///
+/// This makes sure that the held updates are applied, even if there is no other trigger in the window.
+pub struct WindowTrigger {
+    windows: DeployWindows,
+}
+
+impl WindowTrigger {
+    /// Creates a new WindowTrigger with the deploy windows.
+    pub fn new(windows: DeployWindows) -> Self {
+        Self { windows }
+    }
+}
+
+impl Trigger for WindowTrigger {
+    /// Starts a trigger that waits until the next deploy window opens and triggers then.
+    fn listen(&self, tx: Sender<Option<TriggerMessage>>) -> Result<(), TriggerError> {
+        info!(
+            "Starting to trigger when the deploy windows open ({}).",
+            self.windows
+        );
+
+        while let Some(next_opening) = self.windows.find_next_opening(Utc::now()) {
+            debug!("Next deploy window opens at {}.", next_opening.to_rfc3339());
+            while let Ok(remaining) = (next_opening - Utc::now()).to_std() {
+                sleep(remaining);
+            }
+
+            let context: Context = HashMap::from([("TRIGGER_NAME", TRIGGER_NAME.to_string())]);
+            tx.send(Some(context.into()))?;
+        }
+
+        debug!("The deploy windows never open or close, stopping the trigger.");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn it_should_stop_if_the_window_is_always_open() {
+        let windows = DeployWindows::new(vec!["00:00-24:00".parse().unwrap()], None).unwrap();
+        let trigger = WindowTrigger::new(windows);
+        let (tx, rx) = mpsc::channel::<Option<TriggerMessage>>();
+
+        trigger.listen(tx).unwrap();
+
+        assert!(rx.try_recv().is_err());
+    }
+}